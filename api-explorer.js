@@ -0,0 +1,117 @@
+const specUrl = document.body.dataset.spec;
+
+function element(tag, className, text) {
+  const el = document.createElement(tag);
+  if (className) el.className = className;
+  if (text !== undefined) el.textContent = text;
+  return el;
+}
+
+function resolve(spec, schema) {
+  if (schema && schema.$ref) {
+    return spec.components.schemas[schema.$ref.split("/").pop()];
+  }
+  return schema;
+}
+
+function exampleFor(spec, schema) {
+  schema = resolve(spec, schema);
+  if (!schema) return null;
+  if (schema.type === "object" || schema.properties) {
+    const value = {};
+    for (const [name, property] of Object.entries(schema.properties || {})) {
+      value[name] = exampleFor(spec, property);
+    }
+    return value;
+  }
+  if (schema.type === "array") return [exampleFor(spec, schema.items)];
+  if (schema.type === "integer" || schema.type === "number") return 0;
+  if (schema.type === "boolean") return false;
+  return "";
+}
+
+async function send(operation, inputs, bodyInput, fileInput, output) {
+  let path = operation.path;
+  const query = new URLSearchParams();
+  const headers = {};
+  for (const [param, input] of inputs) {
+    if (input.value === "") continue;
+    if (param.in === "path") path = path.replace(`{${param.name}}`, encodeURIComponent(input.value));
+    if (param.in === "query") query.append(param.name, input.value);
+    if (param.in === "header") headers[param.name] = input.value;
+  }
+
+  const apiKey = document.getElementById("api-key").value;
+  if (apiKey) headers["authorization"] = `Bearer ${apiKey}`;
+
+  let body;
+  if (fileInput) {
+    body = new FormData();
+    for (const file of fileInput.files) body.append("files", file);
+  } else if (bodyInput) {
+    headers["content-type"] = "application/json";
+    body = bodyInput.value;
+  }
+
+  const url = query.toString() ? `${path}?${query}` : path;
+  output.textContent = "…";
+  try {
+    const res = await fetch(url, { method: operation.method.toUpperCase(), headers, body, credentials: "same-origin" });
+    const text = await res.text();
+    let pretty = text;
+    try { pretty = JSON.stringify(JSON.parse(text), null, 2); } catch (_) { /* not JSON */ }
+    output.textContent = `${res.status} ${res.statusText}\n\n${pretty}`;
+  } catch (error) {
+    output.textContent = `Request failed: ${error}`;
+  }
+}
+
+function renderOperation(spec, operation) {
+  const box = element("details", "api-operation");
+  const summary = element("summary");
+  summary.append(element("span", `api-method api-${operation.method}`, operation.method.toUpperCase()), ` ${operation.path}`);
+  box.append(summary);
+
+  const inputs = [];
+  for (const param of operation.parameters || []) {
+    const label = element("label", "api-param", `${param.name} (${param.in}${param.required ? ", required" : ""})`);
+    const input = element("input");
+    input.placeholder = param.description || "";
+    label.append(input);
+    box.append(label);
+    inputs.push([param, input]);
+  }
+
+  let bodyInput = null;
+  let fileInput = null;
+  const content = operation.requestBody && operation.requestBody.content;
+  if (content && content["multipart/form-data"]) {
+    fileInput = element("input");
+    fileInput.type = "file";
+    fileInput.multiple = true;
+    box.append(fileInput);
+  } else if (content && content["application/json"]) {
+    bodyInput = element("textarea", "api-body");
+    bodyInput.value = JSON.stringify(exampleFor(spec, content["application/json"].schema), null, 2);
+    box.append(bodyInput);
+  }
+
+  const output = element("pre", "api-output");
+  const button = element("button", "download-btn", "Send");
+  button.addEventListener("click", () => send(operation, inputs, bodyInput, fileInput, output));
+  box.append(button, output);
+  return box;
+}
+
+fetch(specUrl)
+  .then(res => res.json())
+  .then(spec => {
+    document.getElementById("api-description").textContent = spec.info.description || "";
+    const operations = document.getElementById("operations");
+    for (const [path, item] of Object.entries(spec.paths)) {
+      for (const [method, operation] of Object.entries(item)) {
+        operations.append(renderOperation(spec, { ...operation, path, method }));
+      }
+    }
+  })
+  .catch(error => console.error('Error:', error));
@@ -0,0 +1,95 @@
+use std::fmt;
+use std::io;
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The server refused the request.
+    /// `code` is the machine readable code of the JSON API, the plain `/f` routes only send a message.
+    Api {
+        status: StatusCode,
+        code: Option<String>,
+        message: String,
+    },
+    Http(reqwest::Error),
+    Io(io::Error),
+    /// The server answered with something this client does not understand
+    Protocol(String),
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: ErrorDetail,
+}
+
+#[derive(Deserialize)]
+struct ErrorDetail {
+    code: String,
+    message: String,
+}
+
+impl Error {
+    /// Reads the error out of a non success response
+    pub(crate) async fn from_response(res: reqwest::Response) -> Error {
+        let status = res.status();
+        let body = match res.text().await {
+            Ok(body) => body,
+            Err(err) => return Error::Http(err),
+        };
+        match serde_json::from_str::<ErrorBody>(&body) {
+            Ok(body) => Error::Api { status, code: Some(body.error.code), message: body.error.message },
+            Err(_) => Error::Api { status, code: None, message: body.trim().to_string() },
+        }
+    }
+
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Error::Api { status, .. } => Some(*status),
+            Error::Http(err) => err.status(),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            Error::Api { code, .. } => code.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Api { status, message, .. } => write!(f, "{status}: {message}"),
+            Error::Http(err) => write!(f, "{err}"),
+            Error::Io(err) => write!(f, "{err}"),
+            Error::Protocol(message) => write!(f, "Unexpected response: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Http(err) => Some(err),
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Error::Http(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
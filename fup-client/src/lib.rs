@@ -0,0 +1,286 @@
+//! Async client for the fileupload server.
+//!
+//! Sessions are created through the JSON API below `/api/v1`, single raw uploads,
+//! signed links and downloads go through the plain `/f` routes.
+
+mod error;
+mod types;
+
+pub use error::{Error, Result};
+pub use types::{FileEntry, SessionCreated, SessionInfo, SignedLink, Uploaded};
+
+use std::io;
+use std::path::Path;
+use std::pin::Pin;
+use bytes::Bytes;
+use futures_util::{stream, Stream, TryStreamExt};
+use reqwest::multipart::{Form, Part};
+use reqwest::{Body, RequestBuilder, Response, Url};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use tokio_util::io::ReaderStream;
+use crate::types::{Deleted, Extended, FileList, KeySessions};
+
+type ByteStream = Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send>>;
+
+/// One file of an upload, streamed while the request is sent
+pub struct Upload {
+    name: String,
+    length: Option<u64>,
+    stream: ByteStream,
+}
+
+impl Upload {
+    pub fn bytes(name: impl Into<String>, data: impl Into<Bytes>) -> Upload {
+        let data = data.into();
+        Upload {
+            name: name.into(),
+            length: Some(data.len() as u64),
+            stream: Box::pin(stream::once(async move { Ok(data) })),
+        }
+    }
+
+    /// `length` is sent along when known, otherwise the part is streamed without one
+    pub fn stream<S>(name: impl Into<String>, length: Option<u64>, stream: S) -> Upload
+    where
+        S: Stream<Item = io::Result<Bytes>> + Send + 'static,
+    {
+        Upload { name: name.into(), length, stream: Box::pin(stream) }
+    }
+
+    /// Streams a file from disk, named like the file
+    pub async fn file(path: impl AsRef<Path>) -> io::Result<Upload> {
+        let path = path.as_ref();
+        let name = path.file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Path has no file name"))?
+            .to_string_lossy()
+            .to_string();
+        let file = tokio::fs::File::open(path).await?;
+        let length = file.metadata().await?.len();
+        Ok(Upload::stream(name, Some(length), ReaderStream::new(file)))
+    }
+
+    pub fn named(mut self, name: impl Into<String>) -> Upload {
+        self.name = name.into();
+        self
+    }
+
+    /// Calls `progress` with the size of every chunk once it is handed to the connection
+    pub fn on_progress(mut self, progress: impl Fn(u64) + Send + 'static) -> Upload {
+        self.stream = Box::pin(self.stream.inspect_ok(move |chunk| progress(chunk.len() as u64)));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn length(&self) -> Option<u64> {
+        self.length
+    }
+
+    fn into_part(self) -> Part {
+        let body = Body::wrap_stream(self.stream);
+        let part = match self.length {
+            Some(length) => Part::stream_with_length(body, length),
+            None => Part::stream(body),
+        };
+        part.file_name(self.name)
+    }
+}
+
+/// Settings of a new session
+#[derive(Debug, Clone)]
+pub struct CreateOptions {
+    /// Seconds until the session expires
+    pub expires_in: u64,
+    /// Files can only be downloaded through signed links
+    pub signed_only: bool,
+    /// The server replaces zip and tar archives by a folder with their contents
+    pub extract: bool,
+}
+
+impl Default for CreateOptions {
+    fn default() -> Self {
+        CreateOptions { expires_in: 24 * 60 * 60, signed_only: false, extract: false }
+    }
+}
+
+/// A download in progress, the body is read in chunks
+pub struct Download {
+    res: Response,
+}
+
+impl Download {
+    pub fn content_length(&self) -> Option<u64> {
+        self.res.content_length()
+    }
+
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>> {
+        Ok(self.res.chunk().await?)
+    }
+
+    pub async fn bytes(self) -> Result<Bytes> {
+        Ok(self.res.bytes().await?)
+    }
+
+    pub fn into_stream(self) -> impl Stream<Item = Result<Bytes>> {
+        self.res.bytes_stream().map_err(Error::from)
+    }
+}
+
+#[derive(Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    server: Url,
+    api_key: Option<String>,
+}
+
+impl Client {
+    /// `server` is the base URL, e.g. `https://files.example.net`
+    pub fn new(server: &str) -> Result<Client> {
+        let server = Url::parse(server).map_err(|err| Error::Protocol(format!("Invalid server URL {server}: {err}")))?;
+        if server.cannot_be_a_base() {
+            return Err(Error::Protocol(format!("Invalid server URL {server}")))
+        }
+        Ok(Client { http: reqwest::Client::new(), server, api_key: None })
+    }
+
+    /// Sent as bearer token, which applies the key's limits and lets it manage its sessions without owner tokens
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Client {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn server(&self) -> &Url {
+        &self.server
+    }
+
+    fn url(&self, segments: &[&str]) -> Url {
+        let mut url = self.server.clone();
+        url.path_segments_mut().unwrap().pop_if_empty().extend(segments);
+        url
+    }
+
+    async fn send(&self, builder: RequestBuilder, token: Option<&str>) -> Result<Response> {
+        let builder = match &self.api_key {
+            Some(key) => builder.bearer_auth(key),
+            None => builder,
+        };
+        let builder = match token {
+            Some(token) => builder.header("token", token),
+            None => builder,
+        };
+        let res = builder.send().await?;
+        if res.status().is_success() {
+            Ok(res)
+        } else {
+            Err(Error::from_response(res).await)
+        }
+    }
+
+    async fn json<T: DeserializeOwned>(&self, builder: RequestBuilder, token: Option<&str>) -> Result<T> {
+        Ok(self.send(builder, token).await?.json().await?)
+    }
+
+    /// Uploads the files as a new session
+    pub async fn create_session(&self, files: Vec<Upload>, options: &CreateOptions) -> Result<SessionCreated> {
+        let form = files.into_iter().fold(Form::new(), |form, file| form.part("file", file.into_part()));
+        let builder = self.http.post(self.url(&["api", "v1", "sessions"]))
+            .query(&[
+                ("expires_in", options.expires_in.to_string()),
+                ("signed_only", options.signed_only.to_string()),
+                ("extract", options.extract.to_string()),
+            ])
+            .multipart(form);
+        self.json(builder, None).await
+    }
+
+    /// Uploads a single file as the raw request body, like `curl -T`
+    pub async fn upload(&self, file: Upload, options: &CreateOptions) -> Result<Uploaded> {
+        let builder = self.http.put(self.url(&["f", &file.name]))
+            .header("expiration", options.expires_in.to_string())
+            .header("signed-only", options.signed_only.to_string())
+            .header("extract", options.extract.to_string())
+            .body(Body::wrap_stream(file.stream));
+        let res = self.send(builder, None).await?;
+        let header = |name: &str| res.headers().get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+            .ok_or_else(|| Error::Protocol(format!("Missing {name} header")));
+        let (session, token, expiration) = (header("session")?, header("token")?, header("expiration")?);
+        let expires_at = expiration.parse().map_err(|_| Error::Protocol(format!("Invalid expiration {expiration}")))?;
+        let body = res.text().await?;
+        let url = body.lines().next().unwrap_or_default().to_string();
+        Ok(Uploaded { session, token, expires_at, url })
+    }
+
+    /// `token` is the owner token, required for signed-only sessions unless the API key created them
+    pub async fn info(&self, session: &str, token: Option<&str>) -> Result<SessionInfo> {
+        self.json(self.http.get(self.url(&["api", "v1", "sessions", session])), token).await
+    }
+
+    pub async fn files(&self, session: &str, token: Option<&str>) -> Result<Vec<FileEntry>> {
+        let list: FileList = self.json(self.http.get(self.url(&["api", "v1", "sessions", session, "files"])), token).await?;
+        Ok(list.files)
+    }
+
+    /// Adds `by` seconds to the expiration and returns the new one
+    pub async fn extend(&self, session: &str, token: Option<&str>, by: u64) -> Result<u64> {
+        let builder = self.http.post(self.url(&["api", "v1", "sessions", session, "extend"]))
+            .json(&json!({ "by": by }));
+        let extended: Extended = self.json(builder, token).await?;
+        Ok(extended.expires_at)
+    }
+
+    pub async fn delete(&self, session: &str, token: Option<&str>) -> Result<()> {
+        self.send(self.http.delete(self.url(&["api", "v1", "sessions", session])), token).await?;
+        Ok(())
+    }
+
+    /// Sessions created with the API key
+    pub async fn key_sessions(&self) -> Result<Vec<SessionInfo>> {
+        let sessions: KeySessions = self.json(self.http.get(self.url(&["api", "v1", "keys", "me", "sessions"])), None).await?;
+        Ok(sessions.sessions)
+    }
+
+    /// Deletes every session created with the API key and returns how many there were
+    pub async fn delete_key_sessions(&self) -> Result<usize> {
+        let deleted: Deleted = self.json(self.http.delete(self.url(&["api", "v1", "keys", "me", "sessions"])), None).await?;
+        Ok(deleted.deleted)
+    }
+
+    /// A signed link to one file, or to the whole session with `file: None`.
+    /// Only the owner may ask, `expires_in` defaults to the server's link lifetime.
+    pub async fn sign(&self, session: &str, token: &str, file: Option<&str>, expires_in: Option<u64>) -> Result<SignedLink> {
+        let mut builder = self.http.post(self.url(&["f", "sign", session]));
+        if let Some(file) = file {
+            builder = builder.header("file", file);
+        }
+        if let Some(expires_in) = expires_in {
+            builder = builder.header("expires-in", expires_in.to_string());
+        }
+        let res = self.send(builder, Some(token)).await?;
+        let expires_at = res.headers().get("expiration")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(|| Error::Protocol("Missing expiration header".to_string()))?;
+        Ok(SignedLink { url: res.text().await?, expires_at })
+    }
+
+    /// Downloads one file by its `/` separated path, `link` is needed for signed-only sessions
+    pub async fn download(&self, session: &str, file: &str, link: Option<&SignedLink>) -> Result<Download> {
+        let segments: Vec<&str> = ["f", "download", session].into_iter().chain(file.split('/')).collect();
+        self.get_download(self.url(&segments), link).await
+    }
+
+    /// Downloads the whole session as zip archive
+    pub async fn download_all(&self, session: &str, link: Option<&SignedLink>) -> Result<Download> {
+        self.get_download(self.url(&["f", "download", session]), link).await
+    }
+
+    async fn get_download(&self, mut url: Url, link: Option<&SignedLink>) -> Result<Download> {
+        url.set_query(link.and_then(|link| link.query()));
+        Ok(Download { res: self.send(self.http.get(url), None).await? })
+    }
+}
@@ -0,0 +1,81 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FileEntry {
+    /// Path within the session, `/` separated
+    pub name: String,
+    pub size: u64,
+    /// Detected from the content by the server
+    #[serde(default)]
+    pub content_type: Option<String>,
+    /// Listed, but refused for download
+    #[serde(default)]
+    pub quarantined: bool,
+    /// Hex SHA-256 recorded at upload
+    #[serde(default)]
+    pub sha256: Option<String>,
+    #[serde(default)]
+    pub blake3: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SessionCreated {
+    pub session: String,
+    /// Owner token, needed to delete, extend or sign links of the session
+    pub token: String,
+    pub expires_at: u64,
+    pub files: Vec<FileEntry>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SessionInfo {
+    pub session: String,
+    pub expires_at: u64,
+    /// Whether the request proved ownership
+    pub owner: bool,
+    pub signed_only: bool,
+    pub files: Vec<FileEntry>,
+}
+
+/// Answer to a raw `PUT` upload
+#[derive(Debug, Clone)]
+pub struct Uploaded {
+    pub session: String,
+    pub token: String,
+    pub expires_at: u64,
+    /// Download URL of the file, signed for signed-only sessions
+    pub url: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct SignedLink {
+    pub url: String,
+    pub expires_at: u64,
+}
+
+impl SignedLink {
+    /// The `expires`/`sig` part, to be appended to a download of the same server
+    pub fn query(&self) -> Option<&str> {
+        self.url.split_once('?').map(|(_, query)| query)
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct FileList {
+    pub files: Vec<FileEntry>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct KeySessions {
+    pub sessions: Vec<SessionInfo>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct Deleted {
+    pub deleted: usize,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct Extended {
+    pub expires_at: u64,
+}
@@ -0,0 +1,113 @@
+function updateTargetTime() {
+  const days = parseInt(document.getElementById("days").value) || 0;
+  const hours = parseInt(document.getElementById("hours").value) || 0;
+  const minutes = parseInt(document.getElementById("minutes").value) || 0;
+
+  const currentTime = new Date();
+
+  currentTime.setDate(currentTime.getDate() + days);
+  currentTime.setHours(currentTime.getHours() + hours);
+  currentTime.setMinutes(currentTime.getMinutes() + minutes);
+
+  const targetTimeString = currentTime.toUTCString();
+
+  document.getElementById("targetTime").textContent = "Expires at: " + targetTimeString;
+}
+
+updateTargetTime();
+for (const id of ["days", "hours", "minutes"]) {
+    document.getElementById(id).addEventListener("change", updateTargetTime);
+}
+const dropZone = document.getElementById("drop-zone");
+dropZone.addEventListener("dragover", (e) => {
+    e.preventDefault();
+    dropZone.classList.add("dragover");
+});
+
+dropZone.addEventListener("dragleave", () => {
+    dropZone.classList.remove("dragover");
+});
+
+dropZone.addEventListener("drop", async (e) => {
+    e.preventDefault();
+    dropZone.classList.remove("dragover");
+    const entries = [...e.dataTransfer.items]
+        .map((item) => item.webkitGetAsEntry && item.webkitGetAsEntry())
+        .filter((entry) => entry);
+    const files = entries.length > 0
+        ? (await Promise.all(entries.map(collectEntry))).flat()
+        : [...e.dataTransfer.files].map((file) => ({ file, path: file.name }));
+    if (files.length > 10) {
+        alert(`These are too many files (${files.length}). Allowed are up to 10!`)
+        return
+    }
+    if (files.length > 0 && (days || hours || minutes)) {
+        const offset = calculateExpirationOffset(days, hours, minutes);
+        if (offset < 300) {
+        alert("Must expire in at least 5 minutes!")
+    }
+        uploadFiles(files, offset);
+    } else {
+        alert("Please specify a valid expiration time.");
+    }
+});
+
+// Files of a dropped file or folder entry as { file, path } with the path relative to the drop
+async function collectEntry(entry) {
+    if (entry.isFile) {
+        const file = await new Promise((resolve, reject) => entry.file(resolve, reject));
+        return [{ file, path: entry.fullPath.replace(/^\//, "") }];
+    }
+    const reader = entry.createReader();
+    const children = [];
+    // readEntries hands out the folder in batches until it returns an empty one
+    for (;;) {
+        const batch = await new Promise((resolve, reject) => reader.readEntries(resolve, reject));
+        if (batch.length === 0) {
+            break;
+        }
+        children.push(...batch);
+    }
+    return (await Promise.all(children.map(collectEntry))).flat();
+}
+
+function calculateExpirationOffset() {
+    const days = parseInt(document.getElementById("days").value) || 0;
+    const hours = parseInt(document.getElementById("hours").value) || 0;
+    const minutes = parseInt(document.getElementById("minutes").value) || 0;
+
+    const daysInMinutes = days * 24 * 60;
+    const hoursInMinutes = hours * 60;
+    return (daysInMinutes + hoursInMinutes + parseInt(minutes)) * 60;
+}
+
+function uploadFiles(files, expirationOffset) {
+    const formData = new FormData();
+    for (const { file, path } of files) {
+        if (file.size > (10 * 1024 * 1024)) {
+            alert(`This file is too large (${path}), the maximum size is 10MB`)
+            return
+        }
+        formData.append("files", file, path);
+    }
+
+    fetch("upload", {
+        credentials: "same-origin",
+        method: "POST",
+        headers: {
+            "expiration": expirationOffset.toString(),
+            "extract": document.getElementById("extract").checked.toString(),
+        },
+        body: formData,
+    })
+    .then((data) => {
+        console.log(data.headers.get("session"), data.headers)
+        window.location = "session/" + data.headers.get("session")
+    })
+    .catch((error) => {
+        alert("Failed! " + error.code)
+        console.log(error);
+    });
+}
+
+setInterval(updateTargetTime, 1000)
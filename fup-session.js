@@ -0,0 +1,136 @@
+const sessionId = document.body.dataset.sid;
+const hostname = document.body.dataset.hostname;
+const linkQuery = document.body.dataset.linkQuery;
+
+function encodePath(filename) {
+  return filename.split("/").map(encodeURIComponent).join("/");
+}
+
+function downloadFile(filename, _id_) {
+  window.location.href = `/f/download/${sessionId}/${encodePath(filename)}${linkQuery}`;
+  const element = document.getElementById(_id_);
+  element.style.color = 'gold';
+  setTimeout(() => {
+    element.style.color = 'white';
+  }, 1000);
+}
+
+const previewElements = { image: "img", audio: "audio", video: "video", document: "iframe" };
+
+function showPreview(filename, kind) {
+  const element = document.createElement(previewElements[kind]);
+  element.src = `/f/preview/${sessionId}/${encodePath(filename)}${linkQuery}`;
+  if (kind === "audio" || kind === "video") {
+    element.controls = true;
+  }
+  if (kind === "image") {
+    element.alt = filename;
+  }
+  element.className = `preview-${kind}`;
+  document.getElementById("preview-name").textContent = filename;
+  document.getElementById("preview-content").replaceChildren(element);
+  const pane = document.getElementById("preview");
+  pane.hidden = false;
+  pane.scrollIntoView({ behavior: "smooth" });
+}
+
+function closePreview() {
+  document.getElementById("preview-content").replaceChildren();
+  document.getElementById("preview").hidden = true;
+}
+
+function copyLink() {
+  navigator.clipboard.writeText(`${hostname}/f/session/${sessionId}`);
+  const element = document.getElementById("id");
+  element.style.color = 'pink';
+  setTimeout(() => {
+    element.style.color = 'rgb(129, 129, 129)';
+  }, 1000);
+}
+
+function downloadAll() {
+  window.location.href = `/f/download/${sessionId}${linkQuery}`;
+  const element = document.getElementById("id");
+  element.style.color = 'green';
+  setTimeout(() => {
+    element.style.color = 'rgb(129, 129, 129)';
+  }, 1000);
+}
+
+function deleteAll() {
+  fetch(`/f/delete/${sessionId}`, {
+    method: 'POST',
+    credentials: "same-origin",
+    headers: {
+      "csrf-token": document.querySelector('meta[name="csrf-token"]').content
+    }
+  }).then(data => {
+    window.location = "/f/index";
+  }).catch(error => console.error('Error:', error));
+}
+
+function toggleReport() {
+  const form = document.getElementById("report-form");
+  form.hidden = !form.hidden;
+  if (!form.hidden) {
+    form.scrollIntoView({ behavior: "smooth" });
+  }
+}
+
+function sendReport(event) {
+  event.preventDefault();
+  const status = document.getElementById("report-status");
+  fetch(`/f/report/${sessionId}`, {
+    method: 'POST',
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify({
+      reason: document.getElementById("report-reason").value,
+      contact: document.getElementById("report-contact").value
+    })
+  }).then(async response => {
+    status.textContent = await response.text();
+    if (response.ok) {
+      document.getElementById("report-form").reset();
+    }
+  }).catch(error => {
+    status.textContent = "The report could not be sent";
+    console.error('Error:', error);
+  });
+}
+
+document.getElementById("id").addEventListener("click", copyLink);
+document.getElementById("report-open").addEventListener("click", toggleReport);
+document.getElementById("report-form").addEventListener("submit", sendReport);
+document.getElementById("download-all").addEventListener("click", downloadAll);
+document.getElementById("delete-all")?.addEventListener("click", deleteAll);
+document.getElementById("preview-close").addEventListener("click", closePreview);
+for (const button of document.querySelectorAll(".preview-btn")) {
+  button.addEventListener("click", () => showPreview(button.dataset.filename, button.dataset.preview));
+}
+for (const button of document.querySelectorAll(".browse-btn")) {
+  button.addEventListener("click", () => {
+    window.location.href = `/f/browse/${sessionId}/${encodePath(button.dataset.filename)}${linkQuery}`;
+  });
+}
+for (const button of document.querySelectorAll(".view-btn")) {
+  button.addEventListener("click", () => {
+    window.location.href = `/f/view/${sessionId}/${encodePath(button.dataset.filename)}${linkQuery}`;
+  });
+}
+for (const hash of document.querySelectorAll(".file-hash")) {
+  hash.addEventListener("click", () => {
+    navigator.clipboard.writeText(hash.dataset.hash);
+    hash.style.color = 'gold';
+    setTimeout(() => {
+      hash.style.color = '';
+    }, 1000);
+  });
+}
+for (const thumb of document.querySelectorAll(".thumb")) {
+  thumb.loading = "lazy";
+  thumb.addEventListener("error", () => thumb.remove());
+  thumb.src = `/f/thumb/${sessionId}/${encodePath(thumb.dataset.filename)}${linkQuery}`;
+}
+for (const button of document.querySelectorAll(".download-btn")) {
+  button.addEventListener("click", () => downloadFile(button.dataset.filename, button.dataset.target));
+}
@@ -0,0 +1,43 @@
+let anchor = null;
+
+function selectedRange() {
+  const match = window.location.hash.match(/^#L(\d+)(?:-L(\d+))?$/);
+  if (!match) {
+    return null;
+  }
+  const start = Number(match[1]);
+  const end = Number(match[2] ?? match[1]);
+  return [Math.min(start, end), Math.max(start, end)];
+}
+
+function highlightLines() {
+  for (const line of document.querySelectorAll(".code-line.selected")) {
+    line.classList.remove("selected");
+  }
+  const range = selectedRange();
+  if (!range) {
+    return;
+  }
+  for (let n = range[0]; n <= range[1]; n++) {
+    document.getElementById(`L${n}`)?.classList.add("selected");
+  }
+  document.getElementById(`L${range[0]}`)?.scrollIntoView({ block: "center" });
+}
+
+// Shift-click on a line number extends the selection into a range
+for (const link of document.querySelectorAll(".line-number")) {
+  link.addEventListener("click", (event) => {
+    const line = Number(link.textContent);
+    event.preventDefault();
+    if (event.shiftKey && anchor !== null) {
+      history.replaceState(null, "", `#L${Math.min(anchor, line)}-L${Math.max(anchor, line)}`);
+    } else {
+      anchor = line;
+      history.replaceState(null, "", `#L${line}`);
+    }
+    highlightLines();
+  });
+}
+
+window.addEventListener("hashchange", highlightLines);
+highlightLines();
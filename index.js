@@ -0,0 +1,63 @@
+function sleep (time) {
+  return new Promise((resolve) => setTimeout(resolve, time));
+}
+let i = 0;
+const speed = 50;
+
+const texts = [
+  "Create",
+  "Develop",
+  "Maintain",
+  "Improve",
+  "Effective",
+  "Blazingly fast",
+  "Transparent"
+]
+
+let x = Math.floor(Math.random() * texts.length);
+console.log(x);
+let txt = texts[x];
+
+function switchText() {
+  if (x >= texts.length-1) {
+    x = 0
+  } else {
+    x++;
+  }
+  txt = texts[x];
+}
+
+function typeWriterA() {
+  if (i <= txt.length) {
+    sleep(speed).then(() => {
+      if (i !== -1) {
+        document.getElementById("target").innerHTML = txt.slice(0, i);
+      }
+      i++;
+      typeWriterA();
+    })
+  } else {
+    sleep(speed * 50).then(() => {
+      typeWriterB();
+    })
+  }
+}
+
+function typeWriterB() {
+  if (i >= 0) {
+    sleep(speed).then(() => {
+      if (i !== -1) {
+        document.getElementById("target").innerHTML = txt.slice(0, i);
+      }
+      i--;
+      typeWriterB();
+    })
+  } else {
+    sleep(speed * 2).then(() => {
+      switchText();
+      typeWriterA();
+    })
+  }
+}
+
+typeWriterA();
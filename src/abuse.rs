@@ -0,0 +1,281 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::Mutex;
+use actix_web::http::header::{ContentType, AUTHORIZATION, LOCATION, WWW_AUTHENTICATE};
+use actix_web::{web, Error, HttpRequest, HttpResponse};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use lazy_static::lazy_static;
+use pbkdf2::{pbkdf2_hmac, pbkdf2_hmac_array};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+use crate::config::CONFIG;
+use crate::fileupload::{cleanup, is_session_id, list_files_with_sizes, now};
+use crate::signing::{admin_csrf_token, verify_admin_csrf};
+use crate::water::load_admin;
+use crate::{blocklist, client_ip, random_str};
+
+/// The abuse queue, open and resolved reports of all sessions
+const REPORTS_FILE: &str = "reports.json";
+/// Present in a session disabled by reports, it serves nothing until restored
+pub const DISABLED_FILE: &str = ".disabled";
+const REPORT_ID_LEN: usize = 12;
+const MAX_REASON_LEN: usize = 2000;
+const MAX_CONTACT_LEN: usize = 200;
+
+lazy_static! {
+    /// Held while the queue is read, changed and written back
+    static ref QUEUE: Mutex<()> = Mutex::new(());
+}
+
+/// What the admin did about a session's reports
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Resolution {
+    Restored,
+    Deleted,
+    Blocklisted,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Report {
+    pub id: String,
+    pub session: String,
+    pub reason: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contact: Option<String>,
+    /// Address of the client that reported, sessions are disabled by distinct ones only
+    pub reporter: String,
+    pub created: u64,
+    /// `None` while the report waits for review
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<Resolution>,
+}
+
+/// The open reports of one session, as shown on the review page
+pub struct Case {
+    pub session: String,
+    pub disabled: bool,
+    /// `None` once the session expired or was deleted
+    pub files: Option<Vec<(String, u64)>>,
+    pub reports: Vec<Report>,
+}
+
+fn load() -> io::Result<Vec<Report>> {
+    if !fs::exists(REPORTS_FILE)? {
+        return Ok(vec![])
+    }
+    serde_json::from_str(&fs::read_to_string(REPORTS_FILE)?).map_err(io::Error::other)
+}
+
+fn save(reports: &[Report]) -> io::Result<()> {
+    fs::write(REPORTS_FILE, serde_json::to_string_pretty(reports).map_err(io::Error::other)?)
+}
+
+pub fn is_disabled(session_id: &str) -> bool {
+    Path::new(&format!("sessions/{session_id}/{DISABLED_FILE}")).exists()
+}
+
+/// The response for any request to the content of a disabled session
+pub fn refuse_disabled(session_id: &str) -> Option<HttpResponse> {
+    is_disabled(session_id).then(|| HttpResponse::Forbidden().body("This session is disabled while reports about it are reviewed"))
+}
+
+/// Queues a report, one open report per session and client. Returns whether the session got disabled by it.
+fn file_report(session_id: &str, reason: &str, contact: Option<String>, reporter: String) -> io::Result<bool> {
+    let _queue = QUEUE.lock().unwrap();
+    let mut reports = load()?;
+    let open = |report: &&Report| report.session == session_id && report.resolution.is_none();
+    if !reports.iter().filter(open).any(|report| report.reporter == reporter) {
+        reports.push(Report {
+            id: random_str(REPORT_ID_LEN),
+            session: session_id.to_string(),
+            reason: reason.to_string(),
+            contact,
+            reporter,
+            created: now(),
+            resolution: None,
+        });
+        save(&reports)?;
+    }
+    let reporters: HashSet<&str> = reports.iter().filter(open).map(|report| report.reporter.as_str()).collect();
+    let limit = CONFIG.abuse.auto_disable_after;
+    if limit == 0 || reporters.len() < limit || is_disabled(session_id) {
+        return Ok(false)
+    }
+    fs::write(format!("sessions/{session_id}/{DISABLED_FILE}"), "")?;
+    Ok(true)
+}
+
+fn resolve_reports(session_id: &str, resolution: Resolution) -> io::Result<()> {
+    let _queue = QUEUE.lock().unwrap();
+    let mut reports = load()?;
+    for report in reports.iter_mut().filter(|report| report.session == session_id && report.resolution.is_none()) {
+        report.resolution = Some(resolution);
+    }
+    save(&reports)
+}
+
+#[derive(Deserialize)]
+pub struct ReportForm {
+    reason: String,
+    #[serde(default)]
+    contact: Option<String>,
+}
+
+pub async fn report(req: HttpRequest, path: web::Path<String>, form: web::Json<ReportForm>) -> Result<HttpResponse, Error> {
+    let session_id = path.into_inner();
+    if !is_session_id(&session_id) || !fs::exists(format!("sessions/{session_id}"))? {
+        return Ok(HttpResponse::NotFound().body("Non existent session"))
+    }
+    let reason = form.reason.trim();
+    if reason.is_empty() || reason.chars().count() > MAX_REASON_LEN {
+        return Ok(HttpResponse::BadRequest().body(format!("A reason of at most {MAX_REASON_LEN} characters is required")))
+    }
+    let contact = form.contact.as_deref().map(str::trim).filter(|contact| !contact.is_empty());
+    if contact.is_some_and(|contact| contact.chars().count() > MAX_CONTACT_LEN) {
+        return Ok(HttpResponse::BadRequest().body(format!("The contact may have at most {MAX_CONTACT_LEN} characters")))
+    }
+    let reporter = client_ip(&req).unwrap_or_default();
+    if file_report(&session_id, reason, contact.map(str::to_string), reporter)? {
+        println!("Disabled session {session_id} after abuse reports");
+    }
+    Ok(HttpResponse::Accepted().body("Report received, thank you"))
+}
+
+/// `pbkdf2-sha256$<iterations>$<hex salt>$<hex key>`
+const PASSWORD_SCHEME: &str = "pbkdf2-sha256";
+const PASSWORD_ITERATIONS: u32 = 600_000;
+const SALT_LEN: usize = 16;
+
+/// The `admin_password_hash` config value for `password`, with a fresh salt
+fn hash_password(password: &str) -> String {
+    let salt: [u8; SALT_LEN] = rand::random();
+    let key = pbkdf2_hmac_array::<Sha256, 32>(password.as_bytes(), &salt, PASSWORD_ITERATIONS);
+    format!("{PASSWORD_SCHEME}${PASSWORD_ITERATIONS}${}${}", hex::encode(salt), hex::encode(key))
+}
+
+fn verify_password(password: &str, hash: &str) -> bool {
+    let parts: Vec<&str> = hash.split('$').collect();
+    let [PASSWORD_SCHEME, iterations, salt, key] = parts.as_slice() else {
+        return false
+    };
+    let (Ok(iterations), Ok(salt), Ok(expected)) = (iterations.parse::<u32>(), hex::decode(salt), hex::decode(key)) else {
+        return false
+    };
+    let mut key = vec![0; expected.len()];
+    pbkdf2_hmac::<Sha256>(password.as_bytes(), &salt, iterations, &mut key);
+    !expected.is_empty() && bool::from(key.ct_eq(&expected))
+}
+
+/// `fileupload admin-password`, reads the password from stdin and prints its `admin_password_hash`
+pub fn password_cli() -> io::Result<ExitCode> {
+    let mut password = String::new();
+    io::stdin().read_line(&mut password)?;
+    let password = password.trim_end_matches(['\r', '\n']);
+    if password.is_empty() {
+        eprintln!("Usage: echo PASSWORD | fileupload admin-password");
+        return Ok(ExitCode::from(2))
+    }
+    println!("{}", hash_password(password));
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The configured admin user, if the request carries its basic auth credentials.
+/// The password is always hashed and compared in constant time, whatever the user name.
+async fn authenticated_admin(req: &HttpRequest) -> Option<&'static str> {
+    let config = &CONFIG.abuse;
+    let (user, password_hash) = (config.admin_user.as_deref()?, config.admin_password_hash.as_deref()?);
+    let credentials = req.headers().get(AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Basic "))
+        .and_then(|encoded| STANDARD.decode(encoded.trim()).ok())
+        .and_then(|decoded| String::from_utf8(decoded).ok())?;
+    let (name, password) = credentials.split_once(':')?;
+    let name_matches = bool::from(name.as_bytes().ct_eq(user.as_bytes()));
+    let password = password.to_string();
+    let password_matches = web::block(move || verify_password(&password, password_hash)).await.unwrap_or(false);
+    (name_matches & password_matches).then_some(user)
+}
+
+/// Without configured credentials the admin pages do not exist, otherwise they ask for them
+fn admin_refusal() -> HttpResponse {
+    match CONFIG.abuse.admin_user.is_some() && CONFIG.abuse.admin_password_hash.is_some() {
+        true => HttpResponse::Unauthorized()
+            .insert_header((WWW_AUTHENTICATE, "Basic realm=\"fup admin\", charset=\"UTF-8\""))
+            .body("Admin credentials required"),
+        false => HttpResponse::NotFound().finish(),
+    }
+}
+
+/// `/admin/reports`, the open reports grouped by session
+pub async fn review(req: HttpRequest) -> Result<HttpResponse, Error> {
+    let Some(user) = authenticated_admin(&req).await else {
+        return Ok(admin_refusal())
+    };
+    let mut sessions: BTreeMap<String, Vec<Report>> = BTreeMap::new();
+    for report in load()?.into_iter().filter(|report| report.resolution.is_none()) {
+        sessions.entry(report.session.clone()).or_default().push(report);
+    }
+    let mut cases = vec![];
+    for (session, reports) in sessions {
+        let dir = format!("sessions/{session}");
+        let files = match Path::new(&dir).is_dir() {
+            true => Some(list_files_with_sizes(&dir)?),
+            false => None,
+        };
+        cases.push(Case { disabled: is_disabled(&session), session, files, reports });
+    }
+    let html = load_admin(&cases, &admin_csrf_token(user));
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(html))
+}
+
+#[derive(Deserialize)]
+pub struct AdminForm {
+    csrf: String,
+}
+
+/// `/admin/reports/{session}/{action}`: `restore` re-enables the session, `delete` removes it,
+/// `blocklist` adds the hashes of its files to the blocklist and removes it. All of them close its reports.
+pub async fn resolve(req: HttpRequest, path: web::Path<(String, String)>, form: web::Form<AdminForm>) -> Result<HttpResponse, Error> {
+    let Some(user) = authenticated_admin(&req).await else {
+        return Ok(admin_refusal())
+    };
+    if !verify_admin_csrf(user, &form.csrf) {
+        return Ok(HttpResponse::Forbidden().body("Missing or invalid CSRF token"))
+    }
+    let (session_id, action) = path.into_inner();
+    let resolution = match action.as_str() {
+        "restore" => Resolution::Restored,
+        "delete" => Resolution::Deleted,
+        "blocklist" => Resolution::Blocklisted,
+        _ => return Ok(HttpResponse::NotFound().body("Unknown action")),
+    };
+    if !is_session_id(&session_id) {
+        return Ok(HttpResponse::NotFound().body("Non existent session"))
+    }
+    // Sessions may have expired meanwhile, their reports are closed all the same
+    let exists = Path::new(&format!("sessions/{session_id}")).is_dir();
+    match resolution {
+        Resolution::Restored if is_disabled(&session_id) => fs::remove_file(format!("sessions/{session_id}/{DISABLED_FILE}"))?,
+        Resolution::Restored => {}
+        Resolution::Deleted if exists => cleanup(&session_id)?,
+        Resolution::Blocklisted if exists => {
+            // Hashing the files and waiting for the list's lock block, both run off the worker
+            let session = session_id.clone();
+            web::block(move || {
+                for (name, sha256) in blocklist::session_hashes(&session, None)? {
+                    blocklist::add(&sha256, "abuse report", Some(format!("{session}/{name}")))?;
+                }
+                cleanup(&session)
+            }).await??;
+        }
+        Resolution::Deleted | Resolution::Blocklisted => {}
+    }
+    resolve_reports(&session_id, resolution)?;
+    Ok(HttpResponse::SeeOther().insert_header((LOCATION, "/admin/reports")).finish())
+}
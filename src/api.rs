@@ -0,0 +1,517 @@
+use std::fmt::{Display, Formatter};
+use std::fs;
+use actix_multipart::Multipart;
+use actix_web::http::header::ContentType;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse, ResponseError};
+use serde::{Deserialize, Serialize};
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{IntoParams, Modify, OpenApi, ToSchema};
+use crate::apikeys::{ApiKey, KeyError};
+use crate::config::CONFIG;
+use crate::fileupload::{cleanup, create_session, get_expiration_time, is_session_id, is_signed_only, is_token, list_files_with_sizes, now,
+                        session_key, sessions_of_key, SessionOptions, UploadBody, UploadError};
+use crate::{abuse, apikeys, meta};
+use crate::quota::QuotaExceeded;
+use crate::water::get_api_explorer;
+
+pub const API_PREFIX: &str = "/api/v1";
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Fileupload API", description = "Create, inspect and manage file sharing sessions"),
+    paths(create, inspect, list_files, remove, extend, key_sessions, delete_key_sessions),
+    components(schemas(ErrorBody, FileEntry, SessionCreated, SessionInfo, FileList, ExtendRequest, Extended, UploadForm,
+        KeySessions, Deleted)),
+    modifiers(&BearerAuth),
+)]
+pub struct ApiDoc;
+
+/// API keys are sent as `Authorization: Bearer fup_<id>_<secret>`
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        openapi.components.get_or_insert_with(Default::default).add_security_scheme(
+            "api_key",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+/// Every failing `/api/v1` request answers with `{"error": {"code": ..., "message": ...}}`
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ErrorBody<'a> {
+    error: ErrorDetail<'a>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ErrorDetail<'a> {
+    /// Stable identifier, e.g. `not_found` or `upload_quota_exceeded`
+    code: &'a str,
+    message: &'a str,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        ApiError { status, code, message: message.into() }
+    }
+
+    fn bad_request(message: impl Into<String>) -> Self {
+        ApiError::new(StatusCode::BAD_REQUEST, "bad_request", message)
+    }
+
+    fn not_found() -> Self {
+        ApiError::new(StatusCode::NOT_FOUND, "not_found", "Non existent session")
+    }
+
+    fn forbidden() -> Self {
+        ApiError::new(StatusCode::FORBIDDEN, "forbidden", "Invalid auth token")
+    }
+
+    fn disabled() -> Self {
+        ApiError::new(StatusCode::FORBIDDEN, "session_disabled", "This session is disabled while reports about it are reviewed")
+    }
+
+    fn unauthorized() -> Self {
+        ApiError::new(StatusCode::UNAUTHORIZED, "invalid_api_key", "Missing or invalid API key")
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status).json(ErrorBody { error: ErrorDetail { code: self.code, message: &self.message } })
+    }
+}
+
+impl From<std::io::Error> for ApiError {
+    fn from(err: std::io::Error) -> Self {
+        eprintln!("API request failed: {err}");
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "internal", "Internal server error")
+    }
+}
+
+impl From<KeyError> for ApiError {
+    fn from(err: KeyError) -> Self {
+        match err {
+            KeyError::Invalid => ApiError::unauthorized(),
+            KeyError::Io(err) => err.into(),
+        }
+    }
+}
+
+impl From<UploadError> for ApiError {
+    fn from(err: UploadError) -> Self {
+        let code = match &err {
+            UploadError::Invalid(_) => "invalid_upload",
+            UploadError::Unauthorized => "invalid_api_key",
+            UploadError::Quota(QuotaExceeded::Storage) => "storage_quota_exceeded",
+            UploadError::Quota(QuotaExceeded::Client) => "upload_quota_exceeded",
+            UploadError::Rejected(_) => "content_type_rejected",
+            UploadError::Infected(..) => "malware_detected",
+            UploadError::Unscanned => "scanner_unavailable",
+            UploadError::Blocked(_) => "blocked_content",
+            UploadError::Failed(_) => "upload_failed",
+        };
+        ApiError::new(err.status(), code, err.message())
+    }
+}
+
+type ApiResult<T> = Result<T, ApiError>;
+
+#[derive(Serialize, ToSchema)]
+pub struct FileEntry {
+    /// Path within the session, `/` separated
+    pub name: String,
+    pub size: u64,
+    /// Detected from the content, absent for files uploaded before detection existed
+    pub content_type: Option<String>,
+    /// Quarantined files are listed but cannot be downloaded
+    pub quarantined: bool,
+    /// Hex SHA-256 of the content as uploaded
+    pub sha256: Option<String>,
+    /// Hex BLAKE3, only if the server computes it
+    pub blake3: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SessionCreated {
+    pub session: String,
+    pub token: String,
+    pub expires_at: u64,
+    pub files: Vec<FileEntry>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SessionInfo {
+    pub session: String,
+    pub expires_at: u64,
+    pub owner: bool,
+    pub signed_only: bool,
+    pub files: Vec<FileEntry>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct FileList {
+    pub files: Vec<FileEntry>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct KeySessions {
+    pub sessions: Vec<SessionInfo>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Deleted {
+    pub deleted: usize,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct CreateQuery {
+    /// Seconds until the session expires, at most the server's maximum lifetime
+    pub expires_in: u64,
+    #[serde(default)]
+    pub signed_only: bool,
+    /// Replace uploaded zip and tar archives by a folder with their contents
+    #[serde(default)]
+    pub extract: bool,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ExtendRequest {
+    /// Seconds added to the current expiration
+    pub by: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Extended {
+    pub session: String,
+    pub expires_at: u64,
+}
+
+/// Multipart body of a new session, one part per file
+#[derive(ToSchema)]
+#[allow(dead_code)]
+struct UploadForm {
+    #[schema(value_type = Vec<String>, format = Binary)]
+    files: Vec<Vec<u8>>,
+}
+
+fn session_dir(session_id: &str) -> ApiResult<String> {
+    let dir = format!("sessions/{session_id}");
+    if is_session_id(session_id) && fs::exists(format!("{dir}/.expiration"))? {
+        Ok(dir)
+    } else {
+        Err(ApiError::not_found())
+    }
+}
+
+/// The owner token or the API key that created the session
+fn is_owner(req: &HttpRequest, session_id: &str) -> bool {
+    let by_token = req.headers().get("token")
+        .and_then(|t| t.to_str().ok())
+        .is_some_and(|token| is_token(session_id, token));
+    by_token || apikeys::authenticate(req).ok().flatten()
+        .is_some_and(|key| session_key(session_id).is_some_and(|id| id == key.id))
+}
+
+fn required_key(req: &HttpRequest) -> ApiResult<ApiKey> {
+    apikeys::authenticate(req)?.ok_or_else(ApiError::unauthorized)
+}
+
+fn files(session_id: &str) -> ApiResult<Vec<FileEntry>> {
+    let mut meta = meta::load(session_id);
+    let mut files: Vec<FileEntry> = list_files_with_sizes(format!("sessions/{session_id}"))?
+        .into_iter()
+        .map(|(name, size)| {
+            let meta = meta.remove(&name);
+            FileEntry {
+                name,
+                size,
+                quarantined: meta.as_ref().is_some_and(|meta| meta.quarantined),
+                sha256: meta.as_ref().and_then(|meta| meta.sha256.clone()),
+                blake3: meta.as_ref().and_then(|meta| meta.blake3.clone()),
+                content_type: meta.map(|meta| meta.content_type),
+            }
+        })
+        .collect();
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(files)
+}
+
+/// Signed-only sessions are not listed to anyone knowing just the ID
+fn visible_files(req: &HttpRequest, session_id: &str) -> ApiResult<Vec<FileEntry>> {
+    if is_signed_only(session_id) && !is_owner(req, session_id) {
+        return Err(ApiError::forbidden())
+    }
+    files(session_id)
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/sessions", tag = "sessions",
+    params(CreateQuery),
+    security((), ("api_key" = [])),
+    request_body(content = UploadForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 201, description = "Session created", body = SessionCreated),
+        (status = 400, description = "Invalid upload", body = ErrorBody),
+        (status = 401, description = "Invalid API key", body = ErrorBody),
+        (status = 403, description = "A file is on the blocklist", body = ErrorBody),
+        (status = 413, description = "Upload quota exceeded", body = ErrorBody),
+        (status = 415, description = "The content policy refuses the type of a file", body = ErrorBody),
+        (status = 422, description = "The malware scanner reported a file", body = ErrorBody),
+        (status = 503, description = "Files cannot be scanned for malware", body = ErrorBody),
+        (status = 507, description = "Storage quota exceeded", body = ErrorBody),
+    ),
+)]
+async fn create(req: HttpRequest, query: web::Query<CreateQuery>, payload: Multipart) -> ApiResult<HttpResponse> {
+    let options = SessionOptions { lifetime: query.expires_in, signed_only: query.signed_only, extract: query.extract };
+    let created = create_session(&req, UploadBody::Multipart(payload), options).await?;
+    let files = files(&created.session_id)?;
+    Ok(HttpResponse::Created().json(SessionCreated {
+        session: created.session_id,
+        token: created.token,
+        expires_at: created.expires,
+        files,
+    }))
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/sessions/{session}", tag = "sessions",
+    security((), ("api_key" = [])),
+    params(
+        ("session" = String, Path, description = "Session ID"),
+        ("token" = Option<String>, Header, description = "Owner token"),
+    ),
+    responses(
+        (status = 200, description = "Session details", body = SessionInfo),
+        (status = 403, description = "Signed-only session and not the owner", body = ErrorBody),
+        (status = 404, description = "Non existent session", body = ErrorBody),
+    ),
+)]
+async fn inspect(req: HttpRequest, path: web::Path<String>) -> ApiResult<HttpResponse> {
+    let session_id = path.into_inner();
+    session_dir(&session_id)?;
+    let files = visible_files(&req, &session_id)?;
+    let owner = is_owner(&req, &session_id);
+    Ok(HttpResponse::Ok().json(session_info(session_id, owner, files)?))
+}
+
+fn session_info(session_id: String, owner: bool, files: Vec<FileEntry>) -> ApiResult<SessionInfo> {
+    Ok(SessionInfo {
+        expires_at: get_expiration_time(format!("sessions/{session_id}"))?.unwrap_or(0),
+        owner,
+        signed_only: is_signed_only(&session_id),
+        session: session_id,
+        files,
+    })
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/sessions/{session}/files", tag = "sessions",
+    security((), ("api_key" = [])),
+    params(
+        ("session" = String, Path, description = "Session ID"),
+        ("token" = Option<String>, Header, description = "Owner token"),
+    ),
+    responses(
+        (status = 200, description = "Files of the session", body = FileList),
+        (status = 403, description = "Signed-only session and not the owner", body = ErrorBody),
+        (status = 404, description = "Non existent session", body = ErrorBody),
+    ),
+)]
+async fn list_files(req: HttpRequest, path: web::Path<String>) -> ApiResult<HttpResponse> {
+    let session_id = path.into_inner();
+    session_dir(&session_id)?;
+    Ok(HttpResponse::Ok().json(FileList { files: visible_files(&req, &session_id)? }))
+}
+
+#[utoipa::path(
+    delete, path = "/api/v1/sessions/{session}", tag = "sessions",
+    security((), ("api_key" = [])),
+    params(
+        ("session" = String, Path, description = "Session ID"),
+        ("token" = Option<String>, Header, description = "Owner token, not needed with the API key that created the session"),
+    ),
+    responses(
+        (status = 204, description = "Session deleted"),
+        (status = 403, description = "Neither the owner token nor the creating API key, or the session is disabled", body = ErrorBody),
+        (status = 404, description = "Non existent session", body = ErrorBody),
+    ),
+)]
+async fn remove(req: HttpRequest, path: web::Path<String>) -> ApiResult<HttpResponse> {
+    let session_id = path.into_inner();
+    session_dir(&session_id)?;
+    if !is_owner(&req, &session_id) {
+        return Err(ApiError::forbidden())
+    }
+    if abuse::is_disabled(&session_id) {
+        return Err(ApiError::disabled())
+    }
+    cleanup(&session_id)?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[utoipa::path(
+    post, path = "/api/v1/sessions/{session}/extend", tag = "sessions",
+    security((), ("api_key" = [])),
+    params(
+        ("session" = String, Path, description = "Session ID"),
+        ("token" = Option<String>, Header, description = "Owner token, not needed with the API key that created the session"),
+    ),
+    request_body = ExtendRequest,
+    responses(
+        (status = 200, description = "New expiration", body = Extended),
+        (status = 400, description = "The session would outlive the server's maximum lifetime", body = ErrorBody),
+        (status = 403, description = "Neither the owner token nor the creating API key, or the session is disabled", body = ErrorBody),
+        (status = 404, description = "Non existent session", body = ErrorBody),
+    ),
+)]
+async fn extend(req: HttpRequest, path: web::Path<String>, body: web::Json<ExtendRequest>) -> ApiResult<HttpResponse> {
+    let session_id = path.into_inner();
+    let dir = session_dir(&session_id)?;
+    if !is_owner(&req, &session_id) {
+        return Err(ApiError::forbidden())
+    }
+    if abuse::is_disabled(&session_id) {
+        return Err(ApiError::disabled())
+    }
+    let limit = now().saturating_add(CONFIG.max_lifetime);
+    let Some(expires_at) = get_expiration_time(&dir)?.unwrap_or_else(now).checked_add(body.by).filter(|at| *at <= limit) else {
+        return Err(ApiError::bad_request(format!("Sessions can live at most {} seconds from now", CONFIG.max_lifetime)))
+    };
+    fs::write(format!("{dir}/.expiration"), expires_at.to_string())?;
+    Ok(HttpResponse::Ok().json(Extended { session: session_id, expires_at }))
+}
+
+#[utoipa::path(
+    get, path = "/api/v1/keys/me/sessions", tag = "api keys",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "Sessions created with the key", body = KeySessions),
+        (status = 401, description = "Missing or invalid API key", body = ErrorBody),
+    ),
+)]
+async fn key_sessions(req: HttpRequest) -> ApiResult<HttpResponse> {
+    let key = required_key(&req)?;
+    let mut sessions = vec![];
+    for session_id in sessions_of_key(&key.id)? {
+        let files = files(&session_id)?;
+        sessions.push(session_info(session_id, true, files)?);
+    }
+    sessions.sort_by_key(|session| session.expires_at);
+    Ok(HttpResponse::Ok().json(KeySessions { sessions }))
+}
+
+#[utoipa::path(
+    delete, path = "/api/v1/keys/me/sessions", tag = "api keys",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "Number of deleted sessions", body = Deleted),
+        (status = 401, description = "Missing or invalid API key", body = ErrorBody),
+    ),
+)]
+async fn delete_key_sessions(req: HttpRequest) -> ApiResult<HttpResponse> {
+    let key = required_key(&req)?;
+    // Disabled sessions stay for the review
+    let sessions: Vec<String> = sessions_of_key(&key.id)?.into_iter().filter(|session_id| !abuse::is_disabled(session_id)).collect();
+    for session_id in &sessions {
+        cleanup(session_id)?;
+    }
+    Ok(HttpResponse::Ok().json(Deleted { deleted: sessions.len() }))
+}
+
+async fn openapi_json() -> HttpResponse {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+async fn explorer() -> ApiResult<HttpResponse> {
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(get_api_explorer()?))
+}
+
+async fn unknown_route() -> ApiResult<HttpResponse> {
+    Err(ApiError::new(StatusCode::NOT_FOUND, "not_found", "Unknown API route"))
+}
+
+/// Defines `ROUTES` and `register_routes` from one table, so the test of the spec sees exactly what is registered
+macro_rules! api_routes {
+    ($($method:ident $path:literal => $handler:ident),* $(,)?) => {
+        /// `(method, path)` of every route below `API_PREFIX`
+        #[cfg(test)]
+        const ROUTES: &[(&str, &str)] = &[$((stringify!($method), $path)),*];
+
+        fn register_routes(cfg: &mut web::ServiceConfig) {
+            $(cfg.route($path, web::$method().to($handler));)*
+        }
+    }
+}
+
+api_routes! {
+    post "/sessions" => create,
+    get "/sessions/{session}" => inspect,
+    delete "/sessions/{session}" => remove,
+    get "/sessions/{session}/files" => list_files,
+    post "/sessions/{session}/extend" => extend,
+    get "/keys/me/sessions" => key_sessions,
+    delete "/keys/me/sessions" => delete_key_sessions,
+    get "/openapi.json" => openapi_json,
+    get "/explorer" => explorer,
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.app_data(web::QueryConfig::default().error_handler(|err, _| ApiError::bad_request(err.to_string()).into()))
+        .app_data(web::JsonConfig::default().error_handler(|err, _| ApiError::bad_request(err.to_string()).into()))
+        .app_data(web::PathConfig::default().error_handler(|err, _| ApiError::bad_request(err.to_string()).into()))
+        .configure(register_routes)
+        .default_service(web::to(unknown_route));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use utoipa::OpenApi;
+    use super::{ApiDoc, API_PREFIX, ROUTES};
+
+    /// The spec and the explorer showing it are not part of the spec
+    const UNDOCUMENTED: [&str; 2] = ["/openapi.json", "/explorer"];
+
+    fn registered_routes() -> BTreeSet<(String, String)> {
+        ROUTES.iter()
+            .filter(|(_, path)| !UNDOCUMENTED.contains(path))
+            .map(|(method, path)| (method.to_string(), format!("{API_PREFIX}{path}")))
+            .collect()
+    }
+
+    fn documented_routes() -> BTreeSet<(String, String)> {
+        let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        spec["paths"].as_object().unwrap().iter()
+            .flat_map(|(path, item)| item.as_object().unwrap().keys().map(move |method| (method.clone(), path.clone())))
+            .collect()
+    }
+
+    #[test]
+    fn spec_matches_routes() {
+        let registered = registered_routes();
+        let documented = documented_routes();
+        assert!(!registered.is_empty());
+        assert_eq!(registered.difference(&documented).collect::<Vec<_>>(), Vec::<&(String, String)>::new(), "registered but not documented");
+        assert_eq!(documented.difference(&registered).collect::<Vec<_>>(), Vec::<&(String, String)>::new(), "documented but not registered");
+    }
+}
@@ -0,0 +1,213 @@
+use std::fs::{self, File};
+use std::io;
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use actix_web::http::header::AUTHORIZATION;
+use actix_web::HttpRequest;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::blobstore::file_id;
+use crate::fileupload::now;
+use crate::random_str;
+
+const KEYS_FILE: &str = "apikeys.json";
+const LOCK_FILE: &str = "apikeys.lock";
+const KEY_PREFIX: &str = "fup";
+const ID_LEN: usize = 8;
+const SECRET_LEN: usize = 32;
+
+/// Identifies a version of the keys file, saving renames a new file over it
+type Version = (SystemTime, Option<(u64, u64)>);
+
+lazy_static! {
+    /// The keys as last parsed for requests, with the version of the file they came from
+    static ref CACHE: Mutex<Option<(Version, Arc<Vec<ApiKey>>)>> = Mutex::new(None);
+}
+
+/// Limits of a key, `None` falls back to the server defaults
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct KeyLimits {
+    pub max_file_size: Option<usize>,
+    pub max_files: Option<usize>,
+    /// Longest lifetime of a session in seconds
+    pub max_lifetime: Option<u64>,
+    /// Bytes per quota window
+    pub quota_bytes: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ApiKey {
+    pub id: String,
+    pub name: String,
+    /// SHA-256 of the secret part, the secret itself is never stored
+    pub hash: String,
+    pub created: u64,
+    #[serde(default)]
+    pub limits: KeyLimits,
+}
+
+pub enum KeyError {
+    /// A bearer token was sent but does not belong to any key
+    Invalid,
+    Io(io::Error),
+}
+
+impl From<io::Error> for KeyError {
+    fn from(err: io::Error) -> Self {
+        KeyError::Io(err)
+    }
+}
+
+fn hash_secret(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+fn load() -> io::Result<Vec<ApiKey>> {
+    if !fs::exists(KEYS_FILE)? {
+        return Ok(vec![])
+    }
+    serde_json::from_str(&fs::read_to_string(KEYS_FILE)?).map_err(io::Error::other)
+}
+
+/// The keys for authenticating requests, parsed again only when the file changed
+fn cached() -> io::Result<Arc<Vec<ApiKey>>> {
+    let metadata = match fs::metadata(KEYS_FILE) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Arc::default()),
+        Err(err) => return Err(err),
+    };
+    let version = (metadata.modified()?, file_id(&metadata));
+    let mut cache = CACHE.lock().unwrap();
+    if let Some((_, keys)) = cache.as_ref().filter(|(cached, _)| *cached == version) {
+        return Ok(keys.clone())
+    }
+    let keys = Arc::new(load()?);
+    *cache = Some((version, keys.clone()));
+    Ok(keys)
+}
+
+/// Written aside and renamed, so requests reading the keys never see half of them
+fn save(keys: &[ApiKey]) -> io::Result<()> {
+    let written = format!("{KEYS_FILE}.{}", random_str(12));
+    fs::write(&written, serde_json::to_string_pretty(keys).map_err(io::Error::other)?)?;
+    fs::rename(&written, KEYS_FILE)
+}
+
+/// Loads, changes and saves the keys while holding `LOCK_FILE`, two admin commands at once would lose a change otherwise
+fn update<T>(change: impl FnOnce(&mut Vec<ApiKey>) -> T) -> io::Result<T> {
+    let lock = File::create(LOCK_FILE)?;
+    lock.lock()?;
+    let mut keys = load()?;
+    let result = change(&mut keys);
+    save(&keys)?;
+    Ok(result)
+}
+
+fn bearer(req: &HttpRequest) -> Option<&str> {
+    req.headers().get(AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(|h| h.trim())
+}
+
+/// Splits `fup_<id>_<secret>`
+fn parse_token(token: &str) -> Option<(&str, &str)> {
+    let rest = token.strip_prefix(KEY_PREFIX)?.strip_prefix('_')?;
+    rest.split_once('_')
+}
+
+/// The key authenticating the request, `Ok(None)` if no bearer token was sent
+pub fn authenticate(req: &HttpRequest) -> Result<Option<ApiKey>, KeyError> {
+    let Some(token) = bearer(req) else {
+        return Ok(None)
+    };
+    let (id, secret) = parse_token(token).ok_or(KeyError::Invalid)?;
+    let hash = hash_secret(secret);
+    cached()?
+        .iter()
+        .find(|key| key.id == id && key.hash == hash)
+        .cloned()
+        .map(Some)
+        .ok_or(KeyError::Invalid)
+}
+
+fn create(name: &str, limits: KeyLimits) -> io::Result<String> {
+    let id = random_str(ID_LEN);
+    let secret = random_str(SECRET_LEN);
+    let key = ApiKey { id: id.clone(), name: name.to_string(), hash: hash_secret(&secret), created: now(), limits };
+    update(|keys| keys.push(key))?;
+    Ok(format!("{KEY_PREFIX}_{id}_{secret}"))
+}
+
+fn delete(id: &str) -> io::Result<bool> {
+    update(|keys| {
+        let before = keys.len();
+        keys.retain(|key| key.id != id);
+        keys.len() != before
+    })
+}
+
+fn limit_str<T: ToString>(limit: &Option<T>) -> String {
+    limit.as_ref().map(|l| l.to_string()).unwrap_or("-".to_string())
+}
+
+const USAGE: &str = "Usage:
+  apikey create <name> [--max-file-size BYTES] [--max-files N] [--max-lifetime SECONDS] [--quota BYTES]
+  apikey list
+  apikey delete <id>";
+
+fn parse_limits(args: &[String]) -> Result<KeyLimits, String> {
+    let mut limits = KeyLimits::default();
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or(format!("Missing value for {flag}"))?;
+        let invalid = |_| format!("Invalid value for {flag}: {value}");
+        match flag.as_str() {
+            "--max-file-size" => limits.max_file_size = Some(value.parse().map_err(invalid)?),
+            "--max-files" => limits.max_files = Some(value.parse().map_err(invalid)?),
+            "--max-lifetime" => limits.max_lifetime = Some(value.parse().map_err(invalid)?),
+            "--quota" => limits.quota_bytes = Some(value.parse().map_err(invalid)?),
+            _ => return Err(format!("Unknown option {flag}")),
+        }
+    }
+    Ok(limits)
+}
+
+/// `fileupload apikey ...`, run instead of the server. Usage errors exit with status 2.
+pub fn cli(args: &[String]) -> io::Result<ExitCode> {
+    match args.iter().map(|a| a.as_str()).collect::<Vec<_>>().as_slice() {
+        ["create", name, ..] => match parse_limits(&args[2..]) {
+            Ok(limits) => {
+                let token = create(name, limits)?;
+                println!("{token}");
+                eprintln!("Store this key now, it cannot be shown again.");
+            }
+            Err(err) => {
+                eprintln!("{err}\n{USAGE}");
+                return Ok(ExitCode::from(2))
+            }
+        },
+        ["list"] => {
+            println!("ID       NAME                 MAX-SIZE   MAX-FILES  MAX-LIFETIME  QUOTA");
+            for key in load()? {
+                println!("{:<8} {:<20} {:<10} {:<10} {:<13} {}", key.id, key.name,
+                         limit_str(&key.limits.max_file_size), limit_str(&key.limits.max_files),
+                         limit_str(&key.limits.max_lifetime), limit_str(&key.limits.quota_bytes));
+            }
+        }
+        ["delete", id] => {
+            if delete(id)? {
+                println!("Deleted {id}");
+            } else {
+                eprintln!("No key with ID {id}");
+            }
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            return Ok(ExitCode::from(2))
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,215 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+use flate2::read::GzDecoder;
+use zip::ZipArchive;
+use crate::fileupload::sanitize_path;
+
+/// Extracted bytes may be at most this many times the size of the archive
+const MAX_COMPRESSION_RATIO: u64 = 100;
+/// Entries shown by the browse view
+const MAX_LISTED_ENTRIES: usize = 10_000;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    /// Guessed from the file name
+    pub fn of(filename: &str) -> Option<ArchiveKind> {
+        let name = filename.to_ascii_lowercase();
+        if name.ends_with(".zip") {
+            Some(ArchiveKind::Zip)
+        } else if name.ends_with(".tar") {
+            Some(ArchiveKind::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveKind::TarGz)
+        } else {
+            None
+        }
+    }
+
+    /// The file name without the archive extension, used as folder for the contents
+    pub fn stem(self, filename: &str) -> &str {
+        let len = filename.len();
+        let ext = match self {
+            ArchiveKind::Zip | ArchiveKind::Tar => 4,
+            ArchiveKind::TarGz if filename.to_ascii_lowercase().ends_with(".tgz") => 4,
+            ArchiveKind::TarGz => 7,
+        };
+        &filename[..len - ext]
+    }
+}
+
+pub enum ArchiveError {
+    TooManyEntries,
+    EntryTooLarge,
+    /// The extracted total exceeds what the upload may still store
+    TooLarge,
+    /// Suspicious compression ratio
+    Ratio,
+    InvalidPath,
+    /// Not a readable archive of its kind
+    Corrupt,
+    Io(io::Error),
+}
+
+impl ArchiveError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            ArchiveError::TooManyEntries => "Archive contains too many files",
+            ArchiveError::EntryTooLarge => "Archive contains a file that is too large",
+            ArchiveError::TooLarge => "Extracted archive exceeds the upload quota",
+            ArchiveError::Ratio => "Archive is compressed suspiciously well",
+            ArchiveError::InvalidPath => "Archive contains an invalid or conflicting path",
+            ArchiveError::Corrupt => "Archive could not be read",
+            ArchiveError::Io(_) => "Archive could not be extracted",
+        }
+    }
+}
+
+impl From<io::Error> for ArchiveError {
+    fn from(err: io::Error) -> Self {
+        ArchiveError::Io(err)
+    }
+}
+
+pub struct Limits {
+    pub max_entries: usize,
+    pub max_entry_size: u64,
+    pub max_total: u64,
+}
+
+#[derive(Default)]
+pub struct Extracted {
+    pub entries: usize,
+    pub bytes: u64,
+    /// Written files, relative to the destination
+    pub paths: Vec<String>,
+}
+
+/// Writes entries below `dest`, keeping track of the limits while the bytes are copied
+struct Extractor<'a> {
+    dest: &'a Path,
+    limits: &'a Limits,
+    max_bytes: u64,
+    extracted: Extracted,
+}
+
+impl Extractor<'_> {
+    fn write(&mut self, name: &str, reader: &mut dyn Read) -> Result<(), ArchiveError> {
+        let relative = sanitize_path(name).ok_or(ArchiveError::InvalidPath)?;
+        self.extracted.entries += 1;
+        if self.extracted.entries > self.limits.max_entries {
+            return Err(ArchiveError::TooManyEntries)
+        }
+        let path = self.dest.join(&relative);
+        let parent_created = path.parent().is_some_and(|parent| fs::create_dir_all(parent).is_ok());
+        if !parent_created || path.exists() {
+            return Err(ArchiveError::InvalidPath)
+        }
+
+        let budget = self.limits.max_entry_size
+            .min(self.limits.max_total.saturating_sub(self.extracted.bytes))
+            .min(self.max_bytes.saturating_sub(self.extracted.bytes));
+        let written = io::copy(&mut reader.take(budget + 1), &mut File::create_new(path)?)?;
+        self.extracted.bytes += written;
+        self.extracted.paths.push(relative);
+        if written > budget {
+            return Err(if written > self.limits.max_entry_size {
+                ArchiveError::EntryTooLarge
+            } else if self.extracted.bytes > self.limits.max_total {
+                ArchiveError::TooLarge
+            } else {
+                ArchiveError::Ratio
+            })
+        }
+        Ok(())
+    }
+}
+
+fn tar_reader(archive: &Path, kind: ArchiveKind) -> io::Result<Box<dyn Read>> {
+    let file = BufReader::new(File::open(archive)?);
+    Ok(match kind {
+        ArchiveKind::TarGz => Box::new(GzDecoder::new(file)),
+        _ => Box::new(file),
+    })
+}
+
+/// Extracts the archive into `dest`, which must not exist yet. Directories, links and other special entries
+/// are skipped, the declared sizes are not trusted. Whatever was written stays behind on errors.
+pub fn extract(archive: &Path, kind: ArchiveKind, dest: &Path, limits: &Limits) -> Result<Extracted, ArchiveError> {
+    let archive_size = fs::metadata(archive)?.len();
+    fs::create_dir(dest).map_err(|_| ArchiveError::InvalidPath)?;
+    let mut extractor = Extractor {
+        dest,
+        limits,
+        max_bytes: archive_size.max(1).saturating_mul(MAX_COMPRESSION_RATIO),
+        extracted: Extracted::default(),
+    };
+
+    match kind {
+        ArchiveKind::Zip => {
+            let mut zip = ZipArchive::new(File::open(archive)?).map_err(|_| ArchiveError::Corrupt)?;
+            for i in 0..zip.len() {
+                let mut entry = zip.by_index(i).map_err(|_| ArchiveError::Corrupt)?;
+                if entry.is_dir() || entry.is_symlink() {
+                    continue
+                }
+                let name = entry.name().to_string();
+                extractor.write(&name, &mut entry)?;
+            }
+        }
+        ArchiveKind::Tar | ArchiveKind::TarGz => {
+            let mut tar = tar::Archive::new(tar_reader(archive, kind)?);
+            for entry in tar.entries().map_err(|_| ArchiveError::Corrupt)? {
+                let mut entry = entry.map_err(|_| ArchiveError::Corrupt)?;
+                if !entry.header().entry_type().is_file() {
+                    continue
+                }
+                let name = entry.path().map_err(|_| ArchiveError::InvalidPath)?.to_string_lossy().to_string();
+                extractor.write(&name, &mut entry)?;
+            }
+        }
+    }
+    Ok(extractor.extracted)
+}
+
+/// Entries of the archive without extracting them, at most `MAX_LISTED_ENTRIES`
+/// as `(name, size, is_dir)`
+pub fn list(archive: &Path, kind: ArchiveKind) -> Result<Vec<(String, u64, bool)>, ArchiveError> {
+    let mut entries = vec![];
+    match kind {
+        ArchiveKind::Zip => {
+            let mut zip = ZipArchive::new(File::open(archive)?).map_err(|_| ArchiveError::Corrupt)?;
+            for i in 0..zip.len().min(MAX_LISTED_ENTRIES) {
+                let entry = zip.by_index_raw(i).map_err(|_| ArchiveError::Corrupt)?;
+                entries.push((entry.name().to_string(), entry.size(), entry.is_dir()));
+            }
+        }
+        ArchiveKind::Tar | ArchiveKind::TarGz => {
+            // Listing reads through the whole stream, decompressing it is capped like extracting
+            let max_bytes = fs::metadata(archive)?.len().max(1).saturating_mul(MAX_COMPRESSION_RATIO);
+            let mut reader = tar_reader(archive, kind)?.take(max_bytes);
+            let listed = list_tar(&mut reader, &mut entries);
+            if reader.limit() == 0 {
+                return Err(ArchiveError::Ratio)
+            }
+            listed?;
+        }
+    }
+    Ok(entries)
+}
+
+fn list_tar(reader: impl Read, entries: &mut Vec<(String, u64, bool)>) -> Result<(), ArchiveError> {
+    let mut tar = tar::Archive::new(reader);
+    for entry in tar.entries().map_err(|_| ArchiveError::Corrupt)?.take(MAX_LISTED_ENTRIES) {
+        let entry = entry.map_err(|_| ArchiveError::Corrupt)?;
+        let name = entry.path().map_err(|_| ArchiveError::Corrupt)?.to_string_lossy().to_string();
+        entries.push((name, entry.size(), entry.header().entry_type().is_dir()));
+    }
+    Ok(())
+}
@@ -0,0 +1,347 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use clap::{Parser, Subcommand};
+use fileupload::parse_lifetime;
+use fup_client::{Client, CreateOptions, FileEntry, SessionCreated, SessionInfo, SignedLink, Upload};
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+const DEFAULT_SERVER: &str = "http://localhost:8080";
+const DEFAULT_EXPIRES: &str = "1d";
+
+type CliResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// Pushes files to and pulls them from a fileupload server
+#[derive(Parser)]
+#[command(name = "fup", version)]
+struct Cli {
+    /// Base URL of the server, overrides the configured one
+    #[arg(long, global = true)]
+    server: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Uploads files and directories as a new session
+    Push {
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Lifetime of the session, a number of seconds or e.g. `30m`, `12h`, `7d`
+        #[arg(short, long, default_value = DEFAULT_EXPIRES)]
+        expires: String,
+        /// Files are only downloadable through signed links
+        #[arg(long)]
+        signed_only: bool,
+        /// Let the server unpack zip and tar archives into folders
+        #[arg(long)]
+        extract: bool,
+    },
+    /// Lists the sessions pushed from here that still exist
+    Ls,
+    /// Shows the files and expiration of a session
+    Info { session: String },
+    /// Downloads a whole session or a single file of it
+    Pull {
+        session: String,
+        file: Option<String>,
+        /// Directory the files are written to
+        #[arg(short, long, default_value = ".")]
+        output: PathBuf,
+    },
+    /// Pushes the expiration of a session back
+    Extend { session: String, by: String },
+    /// Deletes a session
+    Rm { session: String },
+    /// Shows or changes the stored settings, `--server` becomes the default server
+    Config {
+        /// Sent as bearer token with every request, an empty value removes it
+        #[arg(long)]
+        api_key: Option<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct LocalConfig {
+    server: Option<String>,
+    api_key: Option<String>,
+    #[serde(default)]
+    sessions: Vec<StoredSession>,
+}
+
+/// A pushed session together with its owner token
+#[derive(Serialize, Deserialize, Clone)]
+struct StoredSession {
+    server: String,
+    session: String,
+    token: String,
+    expires_at: u64,
+}
+
+/// `FUP_CONFIG`, else `$XDG_CONFIG_HOME/fup/config.json`, else `~/.config/fup/config.json`
+fn config_path() -> PathBuf {
+    if let Some(path) = std::env::var_os("FUP_CONFIG") {
+        return PathBuf::from(path)
+    }
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(std::env::var_os("HOME").unwrap_or_default()).join(".config"));
+    base.join("fup").join("config.json")
+}
+
+impl LocalConfig {
+    fn load() -> io::Result<LocalConfig> {
+        let path = config_path();
+        if !fs::exists(&path)? {
+            return Ok(LocalConfig::default())
+        }
+        serde_json::from_str(&fs::read_to_string(path)?).map_err(io::Error::other)
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let path = config_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self).map_err(io::Error::other)?)
+    }
+
+    fn token(&self, server: &str, session: &str) -> Option<&str> {
+        self.sessions.iter()
+            .find(|s| s.server == server && s.session == session)
+            .map(|s| s.token.as_str())
+    }
+
+    fn forget(&mut self, server: &str, session: &str) {
+        self.sessions.retain(|s| s.server != server || s.session != session);
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+/// Same format the server accepts for raw uploads: seconds with an optional s/m/h/d suffix
+fn parse_duration(value: &str) -> CliResult<u64> {
+    Ok(parse_lifetime(value).ok_or_else(|| format!("Invalid duration: {value}"))?)
+}
+
+fn format_expiration(expires_at: u64) -> String {
+    let remaining = expires_at.saturating_sub(now());
+    match remaining {
+        0 => "expired".to_string(),
+        r if r < 60 * 60 => format!("in {}m", r / 60),
+        r if r < 24 * 60 * 60 => format!("in {}h {}m", r / 3600, r % 3600 / 60),
+        r => format!("in {}d {}h", r / 86400, r % 86400 / 3600),
+    }
+}
+
+/// Accepts a bare session ID or a share link of the session
+fn session_id(session: &str) -> &str {
+    session.trim_end_matches('/').rsplit('/').next().unwrap_or(session)
+}
+
+fn byte_bar(len: u64) -> ProgressBar {
+    let bar = ProgressBar::new(len);
+    bar.set_style(ProgressStyle::with_template("{msg} [{bar:30}] {bytes}/{total_bytes} {bytes_per_sec}")
+        .unwrap()
+        .progress_chars("=> "));
+    bar
+}
+
+/// Uploads the files with one progress bar over all of them
+async fn push(client: &Client, files: &[(String, PathBuf)], options: &CreateOptions) -> CliResult<SessionCreated> {
+    let mut uploads = vec![];
+    for (name, path) in files {
+        uploads.push(Upload::file(path).await?.named(name));
+    }
+    let bar = byte_bar(uploads.iter().filter_map(|u| u.length()).sum());
+    bar.set_message("Uploading");
+    let uploads = uploads.into_iter()
+        .map(|upload| {
+            let bar = bar.clone();
+            upload.on_progress(move |bytes| bar.inc(bytes))
+        })
+        .collect();
+    let created = client.create_session(uploads, options).await?;
+    bar.finish_and_clear();
+    Ok(created)
+}
+
+async fn pull(client: &Client, session: &str, file: &FileEntry, link: Option<&SignedLink>, output: &Path) -> CliResult<()> {
+    let mut download = client.download(session, &file.name, link).await?;
+    let bar = byte_bar(download.content_length().unwrap_or(file.size));
+    bar.set_message(file.name.clone());
+    // Names come from the server, nothing may end up outside of `output`
+    if Path::new(&file.name).components().any(|c| !matches!(c, Component::Normal(_))) {
+        return Err(format!("Refusing to write {}", file.name).into())
+    }
+    let target = output.join(&file.name);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut out = File::create(target)?;
+    let mut hasher = Sha256::new();
+    while let Some(chunk) = download.chunk().await? {
+        out.write_all(&chunk)?;
+        hasher.update(&chunk);
+        bar.inc(chunk.len() as u64);
+    }
+    bar.finish_and_clear();
+    // Files uploaded before the server recorded checksums cannot be checked
+    let actual = hex::encode(hasher.finalize());
+    if file.sha256.as_ref().is_some_and(|expected| *expected != actual) {
+        return Err(format!("Checksum mismatch for {}, the download is corrupted", file.name).into())
+    }
+    Ok(())
+}
+
+/// Regular files below the given paths, named by their path relative to the directory holding the given path,
+/// so `fup push photos` uploads `photos/2024/a.jpg`
+fn collect_files(paths: &[PathBuf]) -> CliResult<Vec<(String, PathBuf)>> {
+    let mut files = vec![];
+    for path in paths {
+        let root = path.canonicalize()?;
+        let base = root.parent().unwrap_or(&root);
+        for entry in WalkDir::new(&root).sort_by_file_name() {
+            let entry = entry?;
+            if entry.file_type().is_file() {
+                let name = entry.path().strip_prefix(base)?.components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                files.push((name, entry.into_path()));
+            }
+        }
+    }
+    if files.is_empty() {
+        return Err("Nothing to upload".into())
+    }
+    Ok(files)
+}
+
+fn print_session(info: &SessionInfo) {
+    println!("Session: {}", info.session);
+    println!("Expires: {}", format_expiration(info.expires_at));
+    if info.signed_only {
+        println!("Signed-only: yes");
+    }
+    if info.owner {
+        println!("Owner: yes");
+    }
+    for file in &info.files {
+        println!("  {:>12}  {}", file.size, file.name);
+    }
+}
+
+async fn run(cli: Cli) -> CliResult<()> {
+    let mut config = LocalConfig::load()?;
+    let server = cli.server.clone().or(config.server.clone())
+        .unwrap_or(DEFAULT_SERVER.to_string())
+        .trim_end_matches('/')
+        .to_string();
+    let mut client = Client::new(&server)?;
+    if let Some(key) = &config.api_key {
+        client = client.with_api_key(key);
+    }
+
+    match cli.command {
+        Command::Push { paths, expires, signed_only, extract } => {
+            let files = collect_files(&paths)?;
+            let options = CreateOptions { expires_in: parse_duration(&expires)?, signed_only, extract };
+            let created = push(&client, &files, &options).await?;
+            config.sessions.push(StoredSession {
+                server: server.clone(),
+                session: created.session.clone(),
+                token: created.token.clone(),
+                expires_at: created.expires_at,
+            });
+            config.save()?;
+            eprintln!("Uploaded {} file(s), expires {}", created.files.len(), format_expiration(created.expires_at));
+            println!("{server}/f/session/{}", created.session);
+        }
+        Command::Ls => {
+            let sessions: Vec<StoredSession> = config.sessions.iter().filter(|s| s.server == server).cloned().collect();
+            for stored in sessions {
+                match client.info(&stored.session, Some(&stored.token)).await {
+                    Ok(info) => println!("{}  {:<14}  {} file(s)", info.session, format_expiration(info.expires_at), info.files.len()),
+                    Err(_) if stored.expires_at <= now() => config.forget(&server, &stored.session),
+                    Err(err) => eprintln!("{}: {err}", stored.session),
+                }
+            }
+            config.save()?;
+        }
+        Command::Info { session } => {
+            let session = session_id(&session);
+            print_session(&client.info(session, config.token(&server, session)).await?);
+        }
+        Command::Pull { session, file, output } => {
+            let session = session_id(&session);
+            let token = config.token(&server, session);
+            let info = client.info(session, token).await?;
+            let files: Vec<&FileEntry> = match &file {
+                Some(name) => vec![info.files.iter().find(|f| &f.name == name).ok_or(format!("No file {name} in {session}"))?],
+                None => info.files.iter().collect(),
+            };
+            if file.is_some() && files[0].quarantined {
+                return Err(format!("{} is quarantined by the server", files[0].name).into())
+            }
+            fs::create_dir_all(&output)?;
+            for file in files {
+                // The server refuses quarantined files, the rest of the session still comes down
+                if file.quarantined {
+                    eprintln!("Skipping {}, quarantined by the server", file.name);
+                    continue
+                }
+                let link = match (info.signed_only, token) {
+                    (true, Some(token)) => Some(client.sign(session, token, Some(&file.name), None).await?),
+                    _ => None,
+                };
+                pull(&client, session, file, link.as_ref(), &output).await?;
+                eprintln!("{}", output.join(&file.name).display());
+            }
+        }
+        Command::Extend { session, by } => {
+            let session = session_id(&session);
+            let expires_at = client.extend(session, config.token(&server, session), parse_duration(&by)?).await?;
+            if let Some(stored) = config.sessions.iter_mut().find(|s| s.server == server && s.session == session) {
+                stored.expires_at = expires_at;
+                config.save()?;
+            }
+            println!("Expires {}", format_expiration(expires_at));
+        }
+        Command::Rm { session } => {
+            let session = session_id(&session);
+            client.delete(session, config.token(&server, session)).await?;
+            config.forget(&server, session);
+            config.save()?;
+            println!("Deleted {session}");
+        }
+        Command::Config { api_key } => {
+            if let Some(server) = cli.server {
+                config.server = Some(server.trim_end_matches('/').to_string());
+            }
+            if let Some(key) = api_key {
+                config.api_key = Some(key).filter(|k| !k.is_empty());
+            }
+            config.save()?;
+            println!("Config: {}", config_path().display());
+            println!("Server: {}", config.server.as_deref().unwrap_or(DEFAULT_SERVER));
+            println!("API key: {}", if config.api_key.is_some() { "set" } else { "-" });
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    if let Err(err) = run(Cli::parse()).await {
+        eprintln!("fup: {err}");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,107 @@
+use std::fs::{self, Metadata};
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::time::sleep;
+use walkdir::WalkDir;
+use crate::meta::SessionMeta;
+use crate::random_str;
+
+/// File contents by SHA-256, session files are hard links to them.
+/// The link count is the reference count: a blob with no other link is garbage.
+pub const BLOB_DIR: &str = "blobs";
+/// Between sweeps for blobs whose release was missed, e.g. because the server stopped
+const GC_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Compressed contents are kept apart from uncompressed ones of the same hash
+fn blob_path(sha256: &str, compressed: bool) -> Option<PathBuf> {
+    let valid = sha256.len() == 64 && sha256.chars().all(|c| c.is_ascii_hexdigit());
+    let name = if compressed { format!("{sha256}.zst") } else { sha256.to_string() };
+    valid.then(|| Path::new(BLOB_DIR).join(&sha256[..2]).join(name))
+}
+
+/// Identifies the file on disk, so links are counted once
+#[cfg(unix)]
+pub fn file_id(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+pub fn file_id(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(unix)]
+fn link_count(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink()
+}
+
+/// Without link counts blobs are never considered unused
+#[cfg(not(unix))]
+fn link_count(_metadata: &Metadata) -> u64 {
+    u64::MAX
+}
+
+/// Makes `file` a link to the blob of its content, `sha256` being the hash of the original content.
+/// A new blob is taken from the file, an existing one replaces it. Where linking fails the file simply keeps its own copy.
+pub fn store(file: &Path, sha256: &str, compressed: bool) -> io::Result<()> {
+    let Some(blob) = blob_path(sha256, compressed) else {
+        return Ok(())
+    };
+    if let Some(parent) = blob.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Linked aside and renamed over the file, so the file is never missing
+    let linked = file.with_file_name(format!(".{}.blob", random_str(12)));
+    match fs::hard_link(&blob, &linked) {
+        Ok(()) => fs::rename(&linked, file),
+        Err(err) if err.kind() == ErrorKind::NotFound => match fs::hard_link(file, &blob) {
+            Err(err) if err.kind() != ErrorKind::AlreadyExists => Err(err),
+            _ => Ok(()),
+        },
+        Err(err) => Err(err),
+    }
+}
+
+fn remove_if_unused(blob: &Path) -> io::Result<bool> {
+    match fs::metadata(blob) {
+        Ok(metadata) if link_count(&metadata) <= 1 => fs::remove_file(blob).map(|_| true),
+        Ok(_) => Ok(false),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Removes the blobs of a deleted session's files that no other session links to
+pub fn release(meta: &SessionMeta) -> io::Result<()> {
+    let blobs = meta.values().filter_map(|meta| blob_path(meta.sha256.as_deref()?, meta.compressed));
+    for blob in blobs {
+        remove_if_unused(&blob)?;
+    }
+    Ok(())
+}
+
+/// Removes every unused blob, returns how many
+pub fn collect_garbage() -> io::Result<usize> {
+    let mut removed = 0;
+    for entry in WalkDir::new(BLOB_DIR).min_depth(2) {
+        let entry = entry?;
+        if entry.file_type().is_file() && remove_if_unused(entry.path())? {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+pub async fn background_gc() {
+    loop {
+        match collect_garbage() {
+            Ok(0) => {}
+            Ok(removed) => println!("Removed {removed} unused blobs"),
+            Err(e) => eprintln!("Error collecting unused blobs: {}", e),
+        }
+        sleep(GC_INTERVAL).await;
+    }
+}
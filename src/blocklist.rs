@@ -0,0 +1,169 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::process::ExitCode;
+use serde::{Deserialize, Serialize};
+use crate::fileupload::{is_session_id, list_files_with_sizes, now};
+use crate::{checksum, compression, meta, random_str};
+
+/// Hashes of files taken down for abuse, uploads containing one of them are refused
+const BLOCKLIST_FILE: &str = "blocklist.json";
+const LOCK_FILE: &str = "blocklist.lock";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BlockedHash {
+    /// Lowercase hex SHA-256
+    pub sha256: String,
+    pub reason: String,
+    pub added: u64,
+    /// `session/file` the hash was taken from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+fn load() -> io::Result<Vec<BlockedHash>> {
+    if !fs::exists(BLOCKLIST_FILE)? {
+        return Ok(vec![])
+    }
+    serde_json::from_str(&fs::read_to_string(BLOCKLIST_FILE)?).map_err(io::Error::other)
+}
+
+/// Written aside and renamed, so uploads reading the list never see half of it
+fn save(entries: &[BlockedHash]) -> io::Result<()> {
+    let written = format!("{BLOCKLIST_FILE}.{}", random_str(12));
+    fs::write(&written, serde_json::to_string_pretty(entries).map_err(io::Error::other)?)?;
+    fs::rename(&written, BLOCKLIST_FILE)
+}
+
+/// Loads, changes and saves the list while holding `LOCK_FILE`. The admin commands run as their own
+/// process next to the server, which changes the list when reports are resolved.
+fn update<T>(change: impl FnOnce(&mut Vec<BlockedHash>) -> T) -> io::Result<T> {
+    let lock = File::create(LOCK_FILE)?;
+    lock.lock()?;
+    let mut entries = load()?;
+    let result = change(&mut entries);
+    save(&entries)?;
+    Ok(result)
+}
+
+fn normalize(sha256: &str) -> Option<String> {
+    (sha256.len() == 64 && sha256.chars().all(|c| c.is_ascii_hexdigit())).then(|| sha256.to_ascii_lowercase())
+}
+
+/// Read once per upload, so changes apply to the next one
+pub fn hashes() -> io::Result<HashSet<String>> {
+    Ok(load()?.into_iter().map(|entry| entry.sha256).collect())
+}
+
+/// Returns whether the hash was new
+pub fn add(sha256: &str, reason: &str, source: Option<String>) -> io::Result<bool> {
+    let Some(sha256) = normalize(sha256) else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Not a SHA-256 hash: {sha256}")))
+    };
+    update(|entries| {
+        if entries.iter().any(|entry| entry.sha256 == sha256) {
+            return false
+        }
+        entries.push(BlockedHash { sha256, reason: reason.to_string(), added: now(), source });
+        true
+    })
+}
+
+pub fn remove(sha256: &str) -> io::Result<bool> {
+    update(|entries| {
+        let before = entries.len();
+        entries.retain(|entry| !entry.sha256.eq_ignore_ascii_case(sha256));
+        entries.len() != before
+    })
+}
+
+/// `(file, sha256)` of the files of a session, or of just `file`.
+/// Files without a recorded hash, from before checksums were kept, are hashed now.
+pub fn session_hashes(session_id: &str, file: Option<&str>) -> io::Result<Vec<(String, String)>> {
+    let dir = Path::new("sessions").join(session_id);
+    if !is_session_id(session_id) || !dir.is_dir() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("No session {session_id}")))
+    }
+    let meta = meta::load(session_id);
+    let mut hashes = vec![];
+    for (name, _) in list_files_with_sizes(&dir)? {
+        if file.is_some_and(|file| file != name) {
+            continue
+        }
+        let file_meta = meta.get(&name);
+        let sha256 = match file_meta.and_then(|meta| meta.sha256.clone()) {
+            Some(sha256) => sha256,
+            None => {
+                let compressed = file_meta.is_some_and(|meta| meta.compressed);
+                checksum::of_reader(compression::open(&dir.join(&name), compressed)?)?.sha256
+            }
+        };
+        hashes.push((name, sha256));
+    }
+    match (file, hashes.is_empty()) {
+        (Some(file), true) => Err(io::Error::new(io::ErrorKind::NotFound, format!("No file {file} in session {session_id}"))),
+        _ => Ok(hashes),
+    }
+}
+
+const USAGE: &str = "Usage:
+  blocklist add <sha256> [--reason TEXT]
+  blocklist add-session <session> [file] [--reason TEXT]
+  blocklist list
+  blocklist remove <sha256>";
+
+/// Splits off `--reason TEXT`
+fn parse_reason(args: &[String]) -> Result<(Vec<&str>, String), String> {
+    let mut rest = vec![];
+    let mut reason = String::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--reason" => reason = args.next().ok_or("Missing value for --reason")?.to_string(),
+            flag if flag.starts_with("--") => return Err(format!("Unknown option {flag}")),
+            arg => rest.push(arg),
+        }
+    }
+    Ok((rest, reason))
+}
+
+/// `fileupload blocklist ...`, run instead of the server. Usage errors exit with status 2.
+pub fn cli(args: &[String]) -> io::Result<ExitCode> {
+    let (args, reason) = match parse_reason(args) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("{err}\n{USAGE}");
+            return Ok(ExitCode::from(2))
+        }
+    };
+    match args.as_slice() {
+        ["add", sha256] => match add(sha256, &reason, None)? {
+            true => println!("Blocked {sha256}"),
+            false => eprintln!("{sha256} is already blocked"),
+        },
+        ["add-session", session, file @ ..] if file.len() <= 1 => {
+            for (name, sha256) in session_hashes(session, file.first().copied())? {
+                match add(&sha256, &reason, Some(format!("{session}/{name}")))? {
+                    true => println!("Blocked {sha256}  {name}"),
+                    false => eprintln!("{sha256}  {name} is already blocked"),
+                }
+            }
+        }
+        ["list"] => {
+            println!("SHA-256                                                          ADDED       SOURCE / REASON");
+            for entry in load()? {
+                println!("{} {:<11} {} {}", entry.sha256, entry.added, entry.source.as_deref().unwrap_or("-"), entry.reason);
+            }
+        }
+        ["remove", sha256] => match remove(sha256)? {
+            true => println!("Removed {sha256}"),
+            false => eprintln!("{sha256} is not blocked"),
+        },
+        _ => {
+            eprintln!("{USAGE}");
+            return Ok(ExitCode::from(2))
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,68 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use crate::config::CONFIG;
+
+/// Hex digests of a file's content
+pub struct Digests {
+    pub sha256: String,
+    /// Only with `blake3_checksums` configured
+    pub blake3: Option<String>,
+}
+
+/// Fed with the chunks of an upload as they arrive
+pub struct Hasher {
+    sha256: Sha256,
+    blake3: Option<blake3::Hasher>,
+}
+
+impl Hasher {
+    pub fn new() -> Self {
+        Hasher {
+            sha256: Sha256::new(),
+            blake3: CONFIG.blake3_checksums.then(blake3::Hasher::new),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.sha256.update(data);
+        if let Some(blake3) = &mut self.blake3 {
+            blake3.update(data);
+        }
+    }
+
+    pub fn finish(self) -> Digests {
+        Digests {
+            sha256: hex::encode(self.sha256.finalize()),
+            blake3: self.blake3.map(|blake3| blake3.finalize().to_hex().to_string()),
+        }
+    }
+}
+
+pub fn of_file(path: &Path) -> io::Result<Digests> {
+    of_reader(File::open(path)?)
+}
+
+pub fn of_reader(mut reader: impl Read) -> io::Result<Digests> {
+    let mut hasher = Hasher::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        match reader.read(&mut buffer)? {
+            0 => return Ok(hasher.finish()),
+            n => hasher.update(&buffer[..n]),
+        }
+    }
+}
+
+/// `sha-256=:…:` as used by `Repr-Digest` (RFC 9530), `None` for a malformed hex digest
+pub fn structured_sha256(sha256: &str) -> Option<String> {
+    hex::decode(sha256).ok().map(|bytes| format!("sha-256=:{}:", STANDARD.encode(bytes)))
+}
+
+/// `SHA-256=…` as used by the older `Digest` header (RFC 3230)
+pub fn legacy_sha256(sha256: &str) -> Option<String> {
+    hex::decode(sha256).ok().map(|bytes| format!("SHA-256={}", STANDARD.encode(bytes)))
+}
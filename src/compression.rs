@@ -0,0 +1,86 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+use actix_web::body::{BodyStream, BoxBody, SizedStream};
+use actix_web::http::header::ACCEPT_ENCODING;
+use actix_web::HttpRequest;
+use async_compression::tokio::bufread::ZstdDecoder;
+use tokio::io::AsyncReadExt;
+use tokio_util::io::ReaderStream;
+use crate::archive::ArchiveKind;
+use crate::config::CONFIG;
+use crate::filetype::type_matches;
+use crate::random_str;
+
+/// Whether a freshly uploaded file is worth storing compressed. Images, media and archives never are,
+/// thumbnails, media previews and archive handling read those files directly.
+pub fn should_compress(filename: &str, content_type: &str, size: u64) -> bool {
+    let config = &CONFIG.compression;
+    let direct = ["image/", "audio/", "video/"].iter().any(|prefix| content_type.starts_with(prefix))
+        || ArchiveKind::of(filename).is_some();
+    config.enabled
+        && size >= config.min_size
+        && !direct
+        && config.types.iter().any(|pattern| type_matches(pattern, content_type))
+}
+
+/// Replaces the file by its zstd compressed form if that is smaller, returns whether it did
+pub fn compress_file(path: &Path) -> io::Result<bool> {
+    let compressed = path.with_file_name(format!(".{}.zst", random_str(12)));
+    zstd::stream::copy_encode(File::open(path)?, File::create(&compressed)?, CONFIG.compression.level)?;
+    if fs::metadata(&compressed)?.len() < fs::metadata(path)?.len() {
+        fs::rename(&compressed, path)?;
+        Ok(true)
+    } else {
+        fs::remove_file(&compressed)?;
+        Ok(false)
+    }
+}
+
+/// The original content of a stored file
+pub fn open(path: &Path, compressed: bool) -> io::Result<Box<dyn Read + Send>> {
+    let file = File::open(path)?;
+    Ok(match compressed {
+        true => Box::new(zstd::stream::read::Decoder::new(file)?),
+        false => Box::new(file),
+    })
+}
+
+/// `zstd` listed in `Accept-Encoding` without `q=0`
+pub fn accepts_zstd(req: &HttpRequest) -> bool {
+    req.headers().get_all(ACCEPT_ENCODING)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|item| {
+            let mut parts = item.split(';');
+            let coding = parts.next().unwrap_or_default().trim();
+            let refused = parts.any(|param| param.trim().strip_prefix("q=").and_then(|q| q.trim().parse::<f32>().ok()) == Some(0.0));
+            coding.eq_ignore_ascii_case("zstd") && !refused
+        })
+}
+
+/// Streams a compressed file as it is stored
+pub async fn stream(path: &Path) -> io::Result<BoxBody> {
+    let file = tokio::fs::File::open(path).await?;
+    let length = file.metadata().await?.len();
+    Ok(BoxBody::new(SizedStream::new(length, ReaderStream::new(file))))
+}
+
+/// Streams the decoded bytes of a compressed file from `start`, `length` of them or all that are left.
+/// The bytes before `start` are decoded and dropped.
+pub async fn stream_decoded(path: &Path, start: u64, length: Option<u64>) -> io::Result<BoxBody> {
+    let file = tokio::fs::File::open(path).await?;
+    let mut decoder = ZstdDecoder::new(tokio::io::BufReader::new(file));
+    tokio::io::copy(&mut (&mut decoder).take(start), &mut tokio::io::sink()).await?;
+    Ok(match length {
+        Some(length) => BoxBody::new(SizedStream::new(length, ReaderStream::new(decoder.take(length)))),
+        None => BoxBody::new(BodyStream::new(ReaderStream::new(decoder))),
+    })
+}
+
+/// Reads the whole original content, for files that are limited in size anyway
+pub fn read(path: &Path, compressed: bool) -> io::Result<Vec<u8>> {
+    let mut content = vec![];
+    BufReader::new(open(path, compressed)?).read_to_end(&mut content)?;
+    Ok(content)
+}
@@ -0,0 +1,328 @@
+use std::fs;
+use std::net::IpAddr;
+use actix_cors::Cors;
+use lazy_static::lazy_static;
+use serde::Deserialize;
+
+const CONFIG_FILE: &str = "fup.toml";
+
+lazy_static! {
+    pub static ref CONFIG: Config = {
+        if fs::exists(CONFIG_FILE).expect("FAILED TO FIND OUT IF fup.toml EXISTS") {
+            let content = fs::read_to_string(CONFIG_FILE).expect("FAILED TO READ fup.toml");
+            toml::from_str(&content).expect("FAILED TO PARSE fup.toml")
+        } else {
+            Config::default()
+        }
+    };
+}
+
+/// Settings read from `fup.toml`; every key is optional.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Peers whose `X-Forwarded-For` / `X-Real-IP` headers are believed
+    pub trusted_proxies: Vec<IpAddr>,
+    pub rate_limit: RateLimitConfig,
+    pub quota: QuotaConfig,
+    pub cors: CorsConfig,
+    pub security_headers: SecurityHeadersConfig,
+    /// Host (with port, if not the default one) that alone serves uploaded files, e.g. `usercontent.example.net`.
+    /// It should not share a registrable domain with the main host, or the owner cookies would reach it.
+    pub content_domain: Option<String>,
+    /// Lifetime of the signed redirects from the main host to the content domain
+    pub content_link_secs: u64,
+    /// Seconds until a session expires when an upload does not say
+    pub default_lifetime: u64,
+    /// Longest a session may live from now, uploads and extensions asking for more are refused
+    pub max_lifetime: u64,
+    pub content_policy: ContentPolicyConfig,
+    /// Also compute BLAKE3 digests of uploads, SHA-256 is always computed
+    pub blake3_checksums: bool,
+    pub compression: CompressionConfig,
+    pub scan: ScanConfig,
+    pub abuse: AbuseConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            trusted_proxies: vec![],
+            rate_limit: RateLimitConfig::default(),
+            quota: QuotaConfig::default(),
+            cors: CorsConfig::default(),
+            security_headers: SecurityHeadersConfig::default(),
+            content_domain: None,
+            content_link_secs: 5 * 60,
+            default_lifetime: 24 * 60 * 60,
+            max_lifetime: 90 * 24 * 60 * 60,
+            content_policy: ContentPolicyConfig::default(),
+            blake3_checksums: false,
+            compression: CompressionConfig::default(),
+            scan: ScanConfig::default(),
+            abuse: AbuseConfig::default(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    pub upload: BucketConfig,
+    pub metadata: BucketConfig,
+    pub download: BucketConfig,
+    /// Consumed by 401/403/404 responses, requests are refused while it is empty
+    pub auth_failure: BucketConfig,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            enabled: true,
+            upload: BucketConfig { capacity: 10.0, refill_per_sec: 0.1 },
+            metadata: BucketConfig { capacity: 60.0, refill_per_sec: 1.0 },
+            download: BucketConfig { capacity: 120.0, refill_per_sec: 2.0 },
+            auth_failure: BucketConfig { capacity: 10.0, refill_per_sec: 0.05 },
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub struct BucketConfig {
+    pub capacity: f64,
+    pub refill_per_sec: f64,
+}
+
+/// Byte limits, `None` means unlimited
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct QuotaConfig {
+    /// Everything stored below `sessions/`
+    pub total_bytes: Option<u64>,
+    /// Uploaded by one client address within `window_secs`
+    pub per_ip_bytes: Option<u64>,
+    /// Uploaded with one API key within `window_secs`
+    pub per_key_bytes: Option<u64>,
+    pub window_secs: u64,
+}
+
+impl Default for QuotaConfig {
+    fn default() -> Self {
+        QuotaConfig {
+            total_bytes: Some(10 * 1024 * 1024 * 1024),
+            per_ip_bytes: Some(500 * 1024 * 1024),
+            per_key_bytes: None,
+            window_secs: 24 * 60 * 60,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct CorsConfig {
+    /// Everything below `/f` and `/api/v1`
+    pub api: CorsPolicy,
+    /// The index, articles and `/r` resources
+    pub pages: CorsPolicy,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        CorsConfig {
+            api: CorsPolicy::default(),
+            pages: CorsPolicy {
+                allowed_origins: vec!["*".to_string()],
+                allowed_methods: vec!["GET".to_string()],
+                allowed_headers: vec![],
+                exposed_headers: vec![],
+                ..CorsPolicy::default()
+            },
+        }
+    }
+}
+
+/// A `"*"` entry in any of the lists allows everything
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct CorsPolicy {
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    pub exposed_headers: Vec<String>,
+    pub allow_credentials: bool,
+    pub max_age: Option<usize>,
+}
+
+impl Default for CorsPolicy {
+    fn default() -> Self {
+        CorsPolicy {
+            allowed_origins: vec![],
+            allowed_methods: vec!["GET".to_string(), "POST".to_string(), "PUT".to_string(), "DELETE".to_string()],
+            allowed_headers: vec![
+                "content-type", "expiration", "session", "token", "csrf-token", "file", "expires-in", "bind-ip", "signed-only", "extract", "checksums", "sha256", "authorization",
+            ].into_iter().map(|h| h.to_string()).collect(),
+            exposed_headers: vec!["session", "token", "expiration", "owner", "repr-digest", "digest"].into_iter().map(|h| h.to_string()).collect(),
+            allow_credentials: false,
+            max_age: Some(3600),
+        }
+    }
+}
+
+impl CorsPolicy {
+    pub fn build(&self) -> Cors {
+        let any = |list: &Vec<String>| list.iter().any(|v| v == "*");
+        let mut cors = Cors::default();
+
+        if any(&self.allowed_origins) {
+            cors = cors.allow_any_origin();
+        } else {
+            for origin in &self.allowed_origins {
+                cors = cors.allowed_origin(origin);
+            }
+        }
+        cors = if any(&self.allowed_methods) {
+            cors.allow_any_method()
+        } else {
+            cors.allowed_methods(self.allowed_methods.iter().map(|m| m.as_str()))
+        };
+        cors = if any(&self.allowed_headers) {
+            cors.allow_any_header()
+        } else {
+            cors.allowed_headers(self.allowed_headers.iter().map(|h| h.as_str()))
+        };
+        cors = if any(&self.exposed_headers) {
+            cors.expose_any_header()
+        } else {
+            cors.expose_headers(self.exposed_headers.iter().map(|h| h.as_str()))
+        };
+        if self.allow_credentials {
+            cors = cors.supports_credentials();
+        }
+        cors.max_age(self.max_age)
+    }
+}
+
+/// What happens to uploads by the type detected from their first bytes, e.g. `quarantine = ["application/x-executable"]`.
+/// Entries are exact types, `type/*` or `*`; files that are neither detected nor UTF-8 are `application/octet-stream`.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct ContentPolicyConfig {
+    /// If not empty, only these types are accepted
+    pub allow: Vec<String>,
+    /// Refused, failing the whole upload
+    pub deny: Vec<String>,
+    /// Stored and listed, but never served
+    pub quarantine: Vec<String>,
+}
+
+/// zstd compression of stored files, clients accepting zstd get them as they are stored
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct CompressionConfig {
+    pub enabled: bool,
+    /// Smaller files are stored as they are
+    pub min_size: u64,
+    pub level: i32,
+    /// Detected types that are compressed, in the form of the content policy. Images, media and archives never are.
+    pub types: Vec<String>,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        CompressionConfig {
+            enabled: false,
+            min_size: 4096,
+            level: 3,
+            types: vec!["text/*".to_string()],
+        }
+    }
+}
+
+/// Malware scanning of every uploaded file, off while no scanner is set
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct ScanConfig {
+    /// Address of a clamd daemon, `unix:/run/clamav/clamd.ctl` or `host:port`
+    pub clamd: Option<String>,
+    /// What happens to files the scanner reports
+    pub action: ScanAction,
+    /// Seconds the daemon may take to take or answer a file
+    pub timeout_secs: u64,
+    /// Refuse uploads while files cannot be scanned, instead of storing them as not scanned
+    pub fail_closed: bool,
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        ScanConfig {
+            clamd: None,
+            action: ScanAction::Block,
+            timeout_secs: 30,
+            fail_closed: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanAction {
+    /// Fail the whole upload
+    Block,
+    /// Stored and listed, but never served
+    Quarantine,
+    /// Served as usual, with a warning on the session page
+    Flag,
+}
+
+/// Reports of sessions by their recipients and the review page at `/admin/reports`
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct AbuseConfig {
+    /// Open reports from distinct clients after which a session is disabled until reviewed, 0 never disables
+    pub auto_disable_after: usize,
+    /// Basic auth user of the review page, which does not exist without user and password
+    pub admin_user: Option<String>,
+    /// Salted hash of the review page password as printed by `fileupload admin-password`
+    pub admin_password_hash: Option<String>,
+}
+
+impl Default for AbuseConfig {
+    fn default() -> Self {
+        AbuseConfig {
+            auto_disable_after: 3,
+            admin_user: None,
+            admin_password_hash: None,
+        }
+    }
+}
+
+/// Headers added to every response that does not set them itself, `None` leaves a header out
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct SecurityHeadersConfig {
+    pub enabled: bool,
+    /// For the pages rendered by the app. With a content domain it is added to `img-src`, `media-src` and `frame-src`.
+    pub content_security_policy: Option<String>,
+    /// For uploaded files served back to clients, previews add a `frame-ancestors` for the main host
+    pub user_content_security_policy: Option<String>,
+    pub referrer_policy: Option<String>,
+    pub strict_transport_security: Option<String>,
+    pub frame_options: Option<String>,
+}
+
+impl Default for SecurityHeadersConfig {
+    fn default() -> Self {
+        SecurityHeadersConfig {
+            enabled: true,
+            content_security_policy: Some("default-src 'none'; script-src 'self'; style-src 'self'; img-src 'self'; \
+                media-src 'self'; frame-src 'self'; connect-src 'self'; form-action 'self'; base-uri 'none'; \
+                frame-ancestors 'none'".to_string()),
+            user_content_security_policy: Some("default-src 'none'; img-src 'self'; media-src 'self'; \
+                style-src 'unsafe-inline'; sandbox".to_string()),
+            referrer_policy: Some("same-origin".to_string()),
+            strict_transport_security: Some("max-age=31536000; includeSubDomains".to_string()),
+            frame_options: Some("DENY".to_string()),
+        }
+    }
+}
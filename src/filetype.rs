@@ -0,0 +1,53 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use crate::config::CONFIG;
+
+/// Bytes of the start of a file looked at to detect its type
+pub const SNIFF_LEN: usize = 8192;
+
+/// What the upload policy does with a detected type
+#[derive(PartialEq, Eq)]
+pub enum Verdict {
+    Accept,
+    Reject,
+    Quarantine,
+}
+
+/// Type of a file by its first bytes. Anything unknown that is valid UTF-8 counts as `text/plain`.
+pub fn detect(head: &[u8]) -> String {
+    if let Some(kind) = infer::get(head) {
+        return kind.mime_type().to_string()
+    }
+    // A multi-byte character may be cut off at the end of the head
+    match std::str::from_utf8(head) {
+        Err(err) if err.error_len().is_some() => "application/octet-stream".to_string(),
+        _ => "text/plain".to_string(),
+    }
+}
+
+pub fn detect_file(path: &Path) -> io::Result<String> {
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    File::open(path)?.take(SNIFF_LEN as u64).read_to_end(&mut head)?;
+    Ok(detect(&head))
+}
+
+/// `pattern` being an exact type, `type/*` or `*`
+pub fn type_matches(pattern: &str, content_type: &str) -> bool {
+    let family = content_type.split('/').next().unwrap_or_default();
+    pattern == "*"
+        || pattern.eq_ignore_ascii_case(content_type)
+        || pattern.strip_suffix("/*").is_some_and(|prefix| prefix.eq_ignore_ascii_case(family))
+}
+
+pub fn verdict(content_type: &str) -> Verdict {
+    let policy = &CONFIG.content_policy;
+    let listed = |patterns: &[String]| patterns.iter().any(|pattern| type_matches(pattern, content_type));
+    if listed(&policy.deny) || (!policy.allow.is_empty() && !listed(&policy.allow)) {
+        Verdict::Reject
+    } else if listed(&policy.quarantine) {
+        Verdict::Quarantine
+    } else {
+        Verdict::Accept
+    }
+}
@@ -1,25 +1,137 @@
 use std::{fs, io};
+use std::collections::HashSet;
 use std::env::temp_dir;
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Seek, Write};
 use std::path::Path;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use actix_files::NamedFile;
+use actix_files::{HttpRange, NamedFile};
 use actix_multipart::Multipart;
-use actix_web::{cookie, web, Error, HttpRequest, HttpResponse, Responder};
-use actix_web::cookie::Cookie;
-use actix_web::http::header::HeaderValue;
+use actix_web::{cookie, web, Error, HttpRequest, HttpResponse, HttpResponseBuilder, Responder};
+use actix_web::http::StatusCode;
+use actix_web::web::Bytes;
+use actix_web::cookie::{Cookie, SameSite};
+use actix_web::http::header::{ContentDisposition, ContentEncoding, ContentType, DispositionParam, DispositionType, HeaderValue, ACCEPT_RANGES, CONTENT_RANGE, RANGE, VARY};
 use actix_web::web::Redirect;
-use futures_util::StreamExt;
+use futures_util::{Stream, StreamExt};
+use mime::Mime;
+use sha2::{Digest, Sha256};
 use tokio::time::sleep;
 use walkdir::{DirEntry, WalkDir};
 use zip::result::ZipError;
 use zip::write::{ExtendedFileOptions, FileOptions};
-use crate::{get_domain, random_str, DEFAULT_RND_STR_LEN};
-use crate::water::{get_fileupload_index, load_all};
+use crate::{abuse, apikeys, archive, blobstore, blocklist, checksum, compression, filetype, meta, scanner, textview, client_ip, get_domain, get_hostname, origin, quota, random_str, DEFAULT_RND_STR_LEN};
+use crate::apikeys::{ApiKey, KeyError};
+use crate::archive::{ArchiveError, ArchiveKind};
+use crate::checksum::{Digests, Hasher};
+use crate::filetype::{Verdict, SNIFF_LEN};
+use crate::meta::{FileMeta, SessionMeta};
+use crate::quota::{Allowance, QuotaExceeded};
+use crate::scanner::ScanStatus;
+use crate::config::{ScanAction, CONFIG};
+use crate::signing::{csrf_token, encode_path, query_param, signed_query, signed_url, verify_csrf, verify_request, LinkScope};
+use crate::water::{get_fileupload_index, load_all, load_archive, load_view};
 
 const MAX_FILE_SIZE: usize = 10 * 1024 * 1024;
 const MAX_FILES: usize = 10;
+const MAX_PATH_DEPTH: usize = 16;
+const RESERVED_FILES: [&str; 7] = [".token", ".expiration", ".signed", ".apikey", ".thumbs", ".meta", ".disabled"];
+/// Textual files mime_guess does not file under `text/*`, previewed as plain text
+const TEXT_EXTENSIONS: [&str; 12] = ["sh", "bash", "yaml", "yml", "ini", "cfg", "conf", "log", "lock", "csv", "tsv", "diff"];
+/// Checksums added to generated archives
+const SUMS_FILE: &str = "SHA256SUMS";
+const DEFAULT_LINK_LIFETIME: u64 = 60 * 60;
+const OWNER_LINK_LIFETIME: u64 = 10 * 60;
+
+fn is_reserved(filename: &str) -> bool {
+    RESERVED_FILES.contains(&filename)
+}
+
+/// Turns an uploaded name into a `/` separated path below the session. `\` counts as separator too,
+/// empty and `.` components are dropped, names with `..`, reserved or control characters are refused.
+pub fn sanitize_path(name: &str) -> Option<String> {
+    let parts: Vec<&str> = name.split(['/', '\\']).filter(|p| !p.is_empty() && *p != ".").collect();
+    let invalid = |part: &&str| *part == ".." || is_reserved(part) || part.chars().any(char::is_control);
+    if parts.is_empty() || parts.len() > MAX_PATH_DEPTH || parts.iter().any(invalid) {
+        return None
+    }
+    Some(parts.join("/"))
+}
+
+/// On disk path of an uploaded file, `None` for anything that is not one
+pub fn session_file(session_id: &str, file: &str) -> Option<String> {
+    let relative = sanitize_path(file).filter(|relative| relative == file)?;
+    let path = format!("sessions/{session_id}/{relative}");
+    (is_session_id(session_id) && Path::new(&path).is_file()).then_some(path)
+}
+
+fn leaf_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+pub fn is_signed_only(session_id: &str) -> bool {
+    Path::new(&format!("sessions/{session_id}/.signed")).exists()
+}
+
+/// Decides whether user bytes may be served for this request, `file` being `None` for the whole session.
+/// With a content domain configured the main host only hands out signed redirects to it.
+pub fn authorize_download(req: &HttpRequest, session_id: &str, file: Option<&str>) -> Option<HttpResponse> {
+    if origin::is_content_host(req) {
+        return refuse_content(session_id, file).or_else(|| (!verify_request(req, session_id, file)).then(signature_error))
+    }
+    authorize_page(req, session_id, file).or_else(|| origin::content_domain().map(|_| origin::redirect(req, session_id, file)))
+}
+
+/// Authorizes the main host's pages that show what is in a file, the text view, the archive listing and verification.
+/// They stay on the main host: the content is escaped into an app page that needs the main host's scripts and styles.
+pub fn authorize_page(req: &HttpRequest, session_id: &str, file: Option<&str>) -> Option<HttpResponse> {
+    refuse_content(session_id, file).or_else(|| (is_signed_only(session_id) && !verify_request(req, session_id, file)).then(signature_error))
+}
+
+fn refuse_content(session_id: &str, file: Option<&str>) -> Option<HttpResponse> {
+    if let Some(res) = abuse::refuse_disabled(session_id) {
+        return Some(res)
+    }
+    file.is_some_and(|file| meta::is_quarantined(session_id, file))
+        .then(|| HttpResponse::Forbidden().body("This file is quarantined"))
+}
+
+fn signature_error() -> HttpResponse {
+    HttpResponse::Forbidden().body("Missing, invalid or expired link signature")
+}
+
+/// Session IDs are generated alphanumeric strings, anything else would escape `sessions/`
+pub fn is_session_id(session_id: &str) -> bool {
+    !session_id.is_empty() && session_id.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+pub fn is_token(session_id: &str, token: &str) -> bool {
+    fs::read_to_string(format!("sessions/{session_id}/.token")).is_ok_and(|t| t == token)
+}
+
+/// The owner token from the session cookie, if it is valid
+fn cookie_owner(req: &HttpRequest, session_id: &str) -> Option<String> {
+    req.cookie(session_id)
+        .map(|c| c.value().to_string())
+        .filter(|token| is_token(session_id, token))
+}
+
+/// Owner-mutating requests either carry the token in the `token` header, which a foreign page cannot set,
+/// or rely on the session cookie and have to echo the page's `csrf-token`.
+fn is_owner_action(req: &HttpRequest, session_id: &str) -> bool {
+    let header = |name| req.headers().get(name).and_then(|v: &HeaderValue| v.to_str().ok());
+    if header("token").is_some_and(|token| is_token(session_id, token)) {
+        return true
+    }
+    match (cookie_owner(req, session_id), header("csrf-token")) {
+        (Some(token), Some(csrf)) => verify_csrf(session_id, &token, csrf),
+        _ => false,
+    }
+}
 
 fn delete_directory_contents<P: AsRef<Path>>(dir: P) -> io::Result<()> {
     if dir.as_ref().is_dir() {
@@ -36,12 +148,15 @@ fn delete_directory_contents<P: AsRef<Path>>(dir: P) -> io::Result<()> {
     Ok(())
 }
 
-fn cleanup(session_id: &String) -> io::Result<()> {
+/// Deletes the session, and the blobs only it used
+pub fn cleanup(session_id: &String) -> io::Result<()> {
+    let meta = meta::load(session_id);
     delete_directory_contents(format!("sessions/{session_id}"))?;
-    fs::remove_dir(format!("sessions/{session_id}"))
+    fs::remove_dir(format!("sessions/{session_id}"))?;
+    blobstore::release(&meta)
 }
 
-fn get_expiration_time<P: AsRef<Path> + std::fmt::Debug>(path: P) -> io::Result<Option<u64>> {
+pub fn get_expiration_time<P: AsRef<Path> + std::fmt::Debug>(path: P) -> io::Result<Option<u64>> {
     let expiration_file = path.as_ref().join(".expiration");
     if !expiration_file.exists() {
         return Ok(None);
@@ -77,7 +192,9 @@ pub async fn cleanup_expired_folders<P: AsRef<Path>>(folder: P) -> io::Result<()
                 let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
                 if now >= expiration_time {
                     println!("Deleting expired folder: {:?}", path);
+                    let meta = meta::load(&entry.file_name().to_string_lossy());
                     wait_for_handles_to_close(&path).await;
+                    blobstore::release(&meta)?;
                 }
             }
         }
@@ -92,84 +209,467 @@ pub async fn background_cleanup(folder_path: &'static str) {
     }
 }
 
-pub async fn upload(req: HttpRequest, mut payload: Multipart) -> Result<HttpResponse, Error> {
-    let session_id = random_str(DEFAULT_RND_STR_LEN);
-    fs::create_dir(format!("sessions/{session_id}"))?;
-    let expiration_opt = req.headers().get("expiration");
-    if expiration_opt.is_none() {
-        cleanup(&session_id).expect("Failed to remove stuff");
-        return Ok(HttpResponse::BadRequest().body("Key not found, expiration"))
+/// A session that has been written to disk
+pub struct Created {
+    pub session_id: String,
+    pub token: String,
+    pub expires: u64,
+}
+
+pub enum UploadError {
+    Invalid(&'static str),
+    /// The request carried an unknown API key
+    Unauthorized,
+    Quota(QuotaExceeded),
+    /// The content policy refuses the detected type
+    Rejected(String),
+    /// The malware scanner reported `(file, signature)` and the scan action blocks
+    Infected(String, String),
+    /// Scanning is required but the scanner failed
+    Unscanned,
+    /// The file's hash is on the blocklist
+    Blocked(String),
+    Failed(Error),
+}
+
+impl UploadError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            UploadError::Invalid(_) => StatusCode::BAD_REQUEST,
+            UploadError::Unauthorized => StatusCode::UNAUTHORIZED,
+            UploadError::Quota(exceeded) => exceeded.status(),
+            UploadError::Rejected(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            UploadError::Infected(..) => StatusCode::UNPROCESSABLE_ENTITY,
+            UploadError::Unscanned => StatusCode::SERVICE_UNAVAILABLE,
+            UploadError::Blocked(_) => StatusCode::FORBIDDEN,
+            UploadError::Failed(err) => err.as_response_error().status_code(),
+        }
     }
-    let expiration = expiration_opt.unwrap().to_str().unwrap().to_string().parse::<u64>();
-    if expiration.is_err() {
-        return Ok(HttpResponse::BadRequest().body("Key is not a u64, expiration"))
+
+    pub fn message(&self) -> String {
+        match self {
+            UploadError::Invalid(message) => message.to_string(),
+            UploadError::Unauthorized => "Invalid API key".to_string(),
+            UploadError::Quota(exceeded) => exceeded.message().to_string(),
+            UploadError::Rejected(content_type) => format!("Files of type {content_type} are not accepted"),
+            UploadError::Infected(file, signature) => format!("{file} was refused by the malware scanner ({signature})"),
+            UploadError::Unscanned => "Files cannot be scanned for malware right now, try again later".to_string(),
+            UploadError::Blocked(file) => format!("{file} matches a file that was removed for abuse"),
+            UploadError::Failed(err) => err.to_string(),
+        }
     }
-    let access_token = random_str(DEFAULT_RND_STR_LEN);
 
-    let mut file_count = 0;
-    while let Some(field) = payload.next().await {
-        let mut field = field?;
-        let content_disposition = field.content_disposition();
-        let filename = content_disposition.unwrap().get_filename().unwrap_or("default.bin");
-        if filename == ".token" || filename == ".expiration" {
-            return Ok(HttpResponse::BadRequest().body("Got filename with reserved name (.token or .expiration)"))
+    pub fn response(&self) -> HttpResponse {
+        HttpResponse::build(self.status()).body(self.message())
+    }
+}
+
+impl<E: Into<Error>> From<E> for UploadError {
+    fn from(err: E) -> Self {
+        UploadError::Failed(err.into())
+    }
+}
+
+/// How a new session is set up
+pub struct SessionOptions {
+    /// Seconds until the session expires
+    pub lifetime: u64,
+    pub signed_only: bool,
+    /// Uploaded zip and tar archives are replaced by a folder with their contents
+    pub extract: bool,
+}
+
+/// Limits shared by all files written into one new session
+struct UploadState {
+    allowance: Allowance,
+    max_file_size: usize,
+    max_files: usize,
+    file_count: usize,
+    session_size: u64,
+    extract: bool,
+    meta: SessionMeta,
+    /// Hashes on the blocklist when the upload started
+    blocklist: HashSet<String>,
+}
+
+impl UploadState {
+    fn new(req: &HttpRequest, key: Option<&ApiKey>, extract: bool) -> Result<Self, UploadError> {
+        let mut allowance = quota::allowance(req, key)?;
+        allowance.check_request(req).map_err(UploadError::Quota)?;
+        let limits = key.map(|key| key.limits.clone()).unwrap_or_default();
+        Ok(UploadState {
+            allowance,
+            max_file_size: limits.max_file_size.unwrap_or(MAX_FILE_SIZE),
+            max_files: limits.max_files.unwrap_or(MAX_FILES),
+            file_count: 0,
+            session_size: 0,
+            extract,
+            meta: SessionMeta::new(),
+            blocklist: blocklist::hashes()?,
+        })
+    }
+
+    /// Applies the content policy to a detected type
+    fn inspect(content_type: String) -> Result<FileMeta, UploadError> {
+        let quarantined = match filetype::verdict(&content_type) {
+            Verdict::Reject => return Err(UploadError::Rejected(content_type)),
+            verdict => verdict == Verdict::Quarantine,
+        };
+        Ok(FileMeta { content_type, quarantined, sha256: None, blake3: None, compressed: false, original_size: None, scan: None })
+    }
+
+    fn check_blocklist(&self, relative: &str, sha256: &str) -> Result<(), UploadError> {
+        match self.blocklist.contains(sha256) {
+            true => Err(UploadError::Blocked(relative.to_string())),
+            false => Ok(()),
+        }
+    }
+
+    /// Applies the scan action to the result of scanning a completely written file
+    fn apply_scan(relative: &str, file_meta: FileMeta, scan: Option<ScanStatus>) -> Result<FileMeta, UploadError> {
+        let quarantined = match &scan {
+            Some(ScanStatus::Infected { signature }) => match CONFIG.scan.action {
+                ScanAction::Block => return Err(UploadError::Infected(relative.to_string(), signature.clone())),
+                ScanAction::Quarantine => true,
+                ScanAction::Flag => file_meta.quarantined,
+            },
+            Some(ScanStatus::Failed) if CONFIG.scan.fail_closed => return Err(UploadError::Unscanned),
+            _ => file_meta.quarantined,
+        };
+        Ok(FileMeta { quarantined, scan, ..file_meta })
+    }
+
+    /// Scans, compresses and deduplicates a completely written file, then notes its metadata.
+    /// The file work runs on the blocking thread pool, scans may wait for the scanner's timeout.
+    async fn finish_file(&mut self, relative: String, path: &Path, file_meta: FileMeta, digests: Digests, size: u64) -> Result<(), UploadError> {
+        let scanned = path.to_path_buf();
+        let scan = web::block(move || scanner::scan_file(&scanned)).await?;
+        let file_meta = Self::apply_scan(&relative, file_meta, scan)?;
+        let compress = compression::should_compress(&relative, &file_meta.content_type, size);
+        let (stored, sha256) = (path.to_path_buf(), digests.sha256.clone());
+        let compressed = web::block(move || -> io::Result<bool> {
+            let compressed = compress && compression::compress_file(&stored)?;
+            blobstore::store(&stored, &sha256, compressed)?;
+            Ok(compressed)
+        }).await??;
+        let file_meta = FileMeta { compressed, original_size: compressed.then_some(size), ..file_meta.with_digests(digests) };
+        self.meta.insert(relative, file_meta);
+        Ok(())
+    }
+
+    async fn write_file<S, E>(&mut self, session_id: &str, filename: &str, mut stream: S) -> Result<(), UploadError>
+    where S: Stream<Item = Result<Bytes, E>> + Unpin, E: Into<Error>
+    {
+        if filename.split(['/', '\\']).any(is_reserved) {
+            return Err(UploadError::Invalid("Got filename with reserved name (.token, .expiration, .signed, .apikey, .thumbs, .meta or .disabled)"))
+        }
+        let Some(relative) = sanitize_path(filename) else {
+            return Err(UploadError::Invalid("Invalid filename"))
+        };
+        self.file_count += 1;
+        if self.file_count > self.max_files {
+            return Err(UploadError::Invalid("Too many files"))
+        }
+        let filepath = Path::new(format!("sessions/{session_id}").as_str()).join(&relative);
+        let parent_created = filepath.parent().is_some_and(|parent| fs::create_dir_all(parent).is_ok());
+        if !parent_created || filepath.is_dir() {
+            return Err(UploadError::Invalid("A file and a directory share a name"))
         }
-        let filepath = Path::new(format!("sessions/{session_id}").as_str()).join(filename);
 
-        let mut file = File::create(filepath)?;
+        // Never opens an existing file, it may be a hard link into the blob store shared with other sessions
+        let mut file = match OpenOptions::new().write(true).create_new(true).open(&filepath) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => return Err(UploadError::Invalid("Duplicate filename")),
+            Err(err) => return Err(err.into()),
+        };
         let mut total_size = 0;
-        while let Some(chunk) = field.next().await {
-            let data = chunk?;
+        // The type is decided as soon as enough bytes are in, refused files are not written any further
+        let mut head = Vec::with_capacity(SNIFF_LEN);
+        let mut file_meta = None;
+        let mut hasher = Hasher::new();
+        while let Some(chunk) = stream.next().await {
+            let data = chunk.map_err(Into::into)?;
             total_size += data.len();
-            if total_size > MAX_FILE_SIZE {
-                cleanup(&session_id).expect("Failed to remove stuff");
-                return Ok(HttpResponse::BadRequest().body("File too large"));
+            self.session_size += data.len() as u64;
+            if total_size > self.max_file_size {
+                return Err(UploadError::Invalid("File too large"))
+            }
+            self.allowance.check(self.session_size).map_err(UploadError::Quota)?;
+            if file_meta.is_none() {
+                head.extend_from_slice(&data[..data.len().min(SNIFF_LEN - head.len())]);
+                if head.len() == SNIFF_LEN {
+                    file_meta = Some(Self::inspect(filetype::detect(&head))?);
+                }
             }
+            hasher.update(&data);
             file.write_all(&data)?;
         }
+        let file_meta = match file_meta {
+            Some(file_meta) => file_meta,
+            None => Self::inspect(filetype::detect(&head))?,
+        };
+        let digests = hasher.finish();
+        self.check_blocklist(&relative, &digests.sha256)?;
+        match ArchiveKind::of(&relative) {
+            Some(kind) if self.extract => self.extract_archive(&filepath, &relative, kind, total_size as u64).await,
+            _ => self.finish_file(relative, &filepath, file_meta, digests, total_size as u64).await,
+        }
+    }
+
+    /// Replaces an archive by a folder named like it, the archive's file slot and bytes go to its contents
+    async fn extract_archive(&mut self, archive: &Path, relative: &str, kind: ArchiveKind, archive_size: u64) -> Result<(), UploadError> {
+        let name = archive.file_name().unwrap_or_default().to_string_lossy().to_string();
+        // The folder is named by the upload too, so it has to pass as an uploaded name
+        let stem = kind.stem(&name);
+        if sanitize_path(stem).is_none_or(|folder| folder != stem) {
+            return Err(UploadError::Invalid("Archive name is not a valid folder name"))
+        }
+        let dest = archive.with_file_name(stem);
+        let others_size = self.session_size - archive_size;
+        let limits = archive::Limits {
+            max_entries: self.max_files - (self.file_count - 1),
+            max_entry_size: self.max_file_size as u64,
+            max_total: self.allowance.remaining().saturating_sub(others_size),
+        };
+        let max_total = limits.max_total;
+        let (source, target) = (archive.to_path_buf(), dest.clone());
+        // Extracted entries are typed and hashed right away, still off the worker
+        let extracted = web::block(move || {
+            let extracted = archive::extract(&source, kind, &target, &limits)?;
+            let mut entries = vec![];
+            for path in &extracted.paths {
+                let path_on_disk = target.join(path);
+                let content_type = filetype::detect_file(&path_on_disk)?;
+                let digests = checksum::of_file(&path_on_disk)?;
+                let size = fs::metadata(&path_on_disk)?.len();
+                entries.push((path.clone(), path_on_disk, content_type, digests, size));
+            }
+            Ok((extracted, entries))
+        }).await?;
+        let (extracted, entries) = match extracted {
+            Ok(extracted) => extracted,
+            Err(ArchiveError::Io(err)) => return Err(err.into()),
+            Err(ArchiveError::TooLarge) => {
+                let exceeded = self.allowance.check(others_size + max_total + 1).err();
+                return Err(exceeded.map(UploadError::Quota).unwrap_or(UploadError::Invalid(ArchiveError::TooLarge.message())))
+            }
+            Err(err) => return Err(UploadError::Invalid(err.message())),
+        };
+        // The limit above was only a snapshot, concurrent uploads may have taken the headroom since
+        if let Err(exceeded) = self.allowance.check(others_size + extracted.bytes) {
+            fs::remove_dir_all(&dest)?;
+            return Err(UploadError::Quota(exceeded))
+        }
+        let folder = match relative.rsplit_once('/') {
+            Some((parent, _)) => format!("{parent}/{stem}"),
+            None => stem.to_string(),
+        };
+        for (path, path_on_disk, content_type, digests, size) in entries {
+            let file_meta = Self::inspect(content_type)?;
+            self.check_blocklist(&format!("{folder}/{path}"), &digests.sha256)?;
+            self.finish_file(format!("{folder}/{path}"), &path_on_disk, file_meta, digests, size).await?;
+        }
+        fs::remove_file(archive)?;
+        self.file_count = self.file_count - 1 + extracted.entries;
+        self.session_size = others_size + extracted.bytes;
+        Ok(())
+    }
+}
+
+pub enum UploadBody {
+    /// One file per part
+    Multipart(Multipart),
+    /// The request body is the file
+    Raw { filename: String, payload: web::Payload },
+}
 
-        file_count += 1;
-        if file_count > MAX_FILES {
-            cleanup(&session_id).expect("Failed to remove stuff");
-            return Ok(HttpResponse::BadRequest().body("Too many files"));
+async fn store_body(req: &HttpRequest, key: Option<&ApiKey>, session_id: &str, body: UploadBody, extract: bool) -> Result<UploadState, UploadError> {
+    let mut state = UploadState::new(req, key, extract)?;
+    match body {
+        UploadBody::Multipart(mut payload) => {
+            while let Some(field) = payload.next().await {
+                let field = field?;
+                let filename = field.content_disposition()
+                    .and_then(|cd| cd.get_filename())
+                    .unwrap_or("default.bin")
+                    .to_string();
+                state.write_file(session_id, &filename, field).await?;
+            }
         }
+        UploadBody::Raw { filename, payload } => state.write_file(session_id, &filename, payload).await?,
     }
+    meta::save(session_id, &state.meta)?;
+    Ok(state)
+}
 
-    let expiration = expiration.unwrap();
+/// Creates a session from an upload body.
+/// Uploads authenticated with an API key use the key's limits. Nothing is left behind on failure.
+pub async fn create_session(req: &HttpRequest, body: UploadBody, options: SessionOptions) -> Result<Created, UploadError> {
+    let SessionOptions { lifetime, signed_only, extract } = options;
+    let key = match apikeys::authenticate(req) {
+        Ok(key) => key,
+        Err(KeyError::Invalid) => return Err(UploadError::Unauthorized),
+        Err(KeyError::Io(err)) => return Err(err.into()),
+    };
+    if key.as_ref().and_then(|key| key.limits.max_lifetime).is_some_and(|max| lifetime > max) {
+        return Err(UploadError::Invalid("Expiration exceeds the limit of the API key"))
+    }
+    let Some(expires) = now().checked_add(lifetime).filter(|_| lifetime <= CONFIG.max_lifetime) else {
+        return Err(UploadError::Invalid("Expiration exceeds the limit of the server"))
+    };
 
-    let mut cookie = Cookie::new(&session_id, &access_token);
+    let session_id = random_str(DEFAULT_RND_STR_LEN);
+    fs::create_dir(format!("sessions/{session_id}"))?;
+    let created = match store_body(req, key.as_ref(), &session_id, body, extract).await {
+        Ok(mut state) => {
+            state.allowance.record(state.session_size);
+            finish_session(session_id.clone(), expires, signed_only, key.as_ref()).map_err(UploadError::from)
+        }
+        Err(err) => Err(err),
+    };
+    if created.is_err() {
+        cleanup(&session_id).expect("Failed to remove stuff");
+    }
+    created
+}
+
+fn finish_session(session_id: String, expires: u64, signed_only: bool, key: Option<&ApiKey>) -> io::Result<Created> {
+    let token = random_str(DEFAULT_RND_STR_LEN);
+    fs::write(format!("sessions/{session_id}/.token"), &token)?;
+    fs::write(format!("sessions/{session_id}/.expiration"), expires.to_string())?;
+    if signed_only {
+        fs::write(format!("sessions/{session_id}/.signed"), "")?;
+    }
+    if let Some(key) = key {
+        fs::write(format!("sessions/{session_id}/.apikey"), &key.id)?;
+    }
+    Ok(Created { session_id, token, expires })
+}
+
+/// The API key a session was created with
+pub fn session_key(session_id: &str) -> Option<String> {
+    fs::read_to_string(format!("sessions/{session_id}/.apikey")).ok()
+}
+
+/// IDs of all sessions created with the API key `key_id`
+pub fn sessions_of_key(key_id: &str) -> io::Result<Vec<String>> {
+    let mut sessions = vec![];
+    for entry in fs::read_dir("sessions")? {
+        let session_id = entry?.file_name().to_string_lossy().to_string();
+        if session_key(&session_id).is_some_and(|id| id == key_id) {
+            sessions.push(session_id);
+        }
+    }
+    Ok(sessions)
+}
+
+pub async fn upload(req: HttpRequest, payload: Multipart) -> Result<HttpResponse, Error> {
+    let Some(expiration) = req.headers().get("expiration") else {
+        return Ok(HttpResponse::BadRequest().body("Key not found, expiration"))
+    };
+    let Some(expiration) = expiration.to_str().ok().and_then(|e| e.parse::<u64>().ok()) else {
+        return Ok(HttpResponse::BadRequest().body("Key is not a u64, expiration"))
+    };
+    let options = SessionOptions {
+        lifetime: expiration,
+        signed_only: req.headers().get("signed-only").is_some_and(|v| v == "true"),
+        extract: req.headers().get("extract").is_some_and(|v| v == "true"),
+    };
+    let created = match create_session(&req, UploadBody::Multipart(payload), options).await {
+        Ok(created) => created,
+        Err(err) => return Ok(err.response()),
+    };
+
+    let mut cookie = Cookie::new(&created.session_id, &created.token);
     cookie.set_max_age(Some(cookie::time::Duration::seconds(expiration as i64)));
     cookie.set_domain(get_domain());
-
-    fs::write(format!("sessions/{session_id}/.token"), &access_token)?;
-    fs::write(format!("sessions/{session_id}/.expiration"), (SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() + expiration).to_string())?;
+    cookie.set_path("/");
+    cookie.set_http_only(true);
+    cookie.set_secure(true);
+    cookie.set_same_site(SameSite::Strict);
 
     Ok(
         HttpResponse::Ok()
-            .append_header(("session".to_string(), session_id.clone()))
-            .append_header(("token".to_string(), access_token.clone()))
+            .append_header(("session".to_string(), created.session_id.clone()))
+            .append_header(("token".to_string(), created.token.clone()))
             .cookie(cookie)
             .body("OK")
     )
 }
 
+/// Parses lifetimes like `3600`, `90m`, `12h` or `7d`, `None` for anything else or more than `u64` seconds.
+/// The `fup` client uses it too.
+pub fn parse_lifetime(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let (number, unit) = match value.char_indices().last()? {
+        (i, 's') => (&value[..i], 1),
+        (i, 'm') => (&value[..i], 60),
+        (i, 'h') => (&value[..i], 60 * 60),
+        (i, 'd') => (&value[..i], 24 * 60 * 60),
+        _ => (value, 1),
+    };
+    number.parse::<u64>().ok()?.checked_mul(unit)
+}
+
+/// `curl -T file https://host/f/` style uploads: the body is a single file, the answer is plain text
+pub async fn upload_raw(req: HttpRequest, path: Option<web::Path<String>>, payload: web::Payload) -> Result<HttpResponse, Error> {
+    let filename = path.map(|p| p.into_inner())
+        .or_else(|| query_param(&req, "name"))
+        .unwrap_or("stdin".to_string());
+    let lifetime = req.headers().get("expiration").and_then(|e| e.to_str().ok()).map(|e| e.to_string())
+        .or_else(|| query_param(&req, "expires"));
+    let lifetime = match lifetime {
+        Some(lifetime) => match parse_lifetime(&lifetime) {
+            Some(lifetime) if lifetime <= CONFIG.max_lifetime => lifetime,
+            Some(_) => return Ok(HttpResponse::BadRequest().body("Expiration exceeds the limit of the server\n")),
+            None => return Ok(HttpResponse::BadRequest().body("Invalid expiration, use seconds or a number with s, m, h or d\n")),
+        },
+        None => CONFIG.default_lifetime,
+    };
+    let signed_only = req.headers().get("signed-only").is_some_and(|v| v == "true");
+    let extract = req.headers().get("extract").is_some_and(|v| v == "true") || query_param(&req, "extract").is_some();
+
+    let options = SessionOptions { lifetime, signed_only, extract };
+    let created = match create_session(&req, UploadBody::Raw { filename: filename.clone(), payload }, options).await {
+        Ok(created) => created,
+        Err(err) => return Ok(HttpResponse::build(err.status()).content_type(ContentType::plaintext()).body(err.message() + "\n")),
+    };
+    let filename = sanitize_path(&filename).unwrap_or(filename);
+    // An extracted archive is gone, the link covers the whole session instead
+    let file = (!extract || ArchiveKind::of(&filename).is_none()).then_some(filename.as_str());
+    let url = if signed_only {
+        let expires = (now() + DEFAULT_LINK_LIFETIME).min(created.expires);
+        signed_url(&LinkScope { session: &created.session_id, file, expires, ip: None })
+    } else if file.is_none() {
+        format!("https://{}/f/session/{}", get_hostname(), created.session_id)
+    } else {
+        format!("https://{}/f/download/{}/{}", get_hostname(), created.session_id, encode_path(&filename))
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::plaintext())
+        .append_header(("session".to_string(), created.session_id.clone()))
+        .append_header(("token".to_string(), created.token.clone()))
+        .append_header(("expiration".to_string(), created.expires.to_string()))
+        .body(format!("{url}\nOwner-Token: {}\n", created.token)))
+}
+
 pub async fn delete(req: HttpRequest, path: web::Path<String>) -> Result<HttpResponse, Error> {
     let session_id = path.into_inner();
-    let token_opt = req.headers().get("token");
     let mut remove = Cookie::new(&session_id, "none");
     remove.set_domain(get_domain());
     remove.set_path("/");
     remove.make_removal();
-    if token_opt.is_none() {
-        return Ok(HttpResponse::BadRequest().body("DEPRECATED Key not found, token"))
+    // Owners cannot get rid of a session while its reports are reviewed
+    if let Some(res) = abuse::refuse_disabled(&session_id) {
+        return Ok(res)
     }
-    let token = token_opt.unwrap().to_str().unwrap().to_string();
-    if fs::read_to_string(format!("sessions/{session_id}/.token")).is_ok_and(|t| {token == t}) {
+    if is_owner_action(&req, &session_id) {
         cleanup(&session_id).expect("Failed to remove entry");
         Ok(HttpResponse::Ok().cookie(remove).body("Removed successfully"))
     }
     else {
-        Ok(HttpResponse::Forbidden().body("Invalid auth token or non existent session"))
+        Ok(HttpResponse::Forbidden().body("Invalid auth token, missing CSRF token or non existent session"))
     }
 }
 
@@ -192,52 +692,68 @@ pub async fn is_entry_owner(req: HttpRequest) -> Result<HttpResponse, Error> {
     }
 }
 
+/// Uploaded files below `dir` as `/` separated relative paths, sorted. Compressed files have their original size.
 pub fn list_files_with_sizes<P: AsRef<Path>>(dir: P) -> io::Result<Vec<(String, u64)>> {
+    let dir = dir.as_ref();
+    let meta = meta::load_in(dir);
     let mut files = Vec::new();
-    if dir.as_ref().is_dir() {
-        for entry in fs::read_dir(dir)? {
+    if dir.is_dir() {
+        let walk = WalkDir::new(dir).min_depth(1).sort_by_file_name().into_iter()
+            .filter_entry(|entry| !is_reserved(&entry.file_name().to_string_lossy()));
+        for entry in walk {
             let entry = entry?;
-            let path = entry.path();
-            if path.is_file() {
-                if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
-                    if file_name != ".token" && file_name != ".expiration" {
-                        let metadata = fs::metadata(&path)?;
-                        files.push((file_name.to_string(), metadata.len()));
-                    }
-                }
+            if entry.file_type().is_file() {
+                let name = entry.path().strip_prefix(dir).unwrap().components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                let size = match meta.get(&name).and_then(|meta| meta.original_size) {
+                    Some(size) => size,
+                    None => entry.metadata()?.len(),
+                };
+                files.push((name, size));
             }
         }
     }
+    files.sort();
     Ok(files)
 }
 
-fn zip_dir<T>(it: &mut dyn Iterator<Item=DirEntry>, prefix: &str, writer: T)
+/// Zips the entries, with a `SHA256SUMS` in `sha256sum` format on top unless a file of that name is included
+fn zip_dir<T>(it: &mut dyn Iterator<Item=DirEntry>, prefix: &str, meta: &SessionMeta, writer: T)
               -> zip::result::ZipResult<()>
 where T: Write+Seek
 {
     let mut zip = zip::ZipWriter::new(writer);
     let options: FileOptions<ExtendedFileOptions> = FileOptions::default();
 
-    let mut buffer = Vec::new();
+    let mut sums = String::new();
+    let mut has_sums_file = false;
     for entry in it {
         let path = entry.path();
         let name = path.strip_prefix(Path::new(prefix)).unwrap();
         if path.is_file() {
             zip.start_file_from_path(name, options.clone())?;
-            let mut f = File::open(path)?;
-
-            f.read_to_end(&mut buffer)?;
+            let name = name.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+            let compressed = meta.get(&name).is_some_and(|meta| meta.compressed);
+            let buffer = compression::read(path, compressed)?;
             zip.write_all(&buffer)?;
-            buffer.clear();
+            has_sums_file |= name == SUMS_FILE;
+            sums.push_str(&format!("{}  {name}\n", hex::encode(Sha256::digest(&buffer))));
         } else if !name.as_os_str().is_empty() {
             zip.add_directory_from_path(name, options.clone())?;
         }
     }
+    if !has_sums_file {
+        zip.start_file(SUMS_FILE, options)?;
+        zip.write_all(sums.as_bytes())?;
+    }
     zip.finish()?;
     Ok(())
 }
 
-fn doit(src_dir: &str, dst_file: &str) -> zip::result::ZipResult<()> {
+/// Zips `src_dir` without the reserved files and the quarantined ones of `meta`
+fn doit(src_dir: &str, dst_file: &str, meta: &SessionMeta) -> zip::result::ZipResult<()> {
     if !Path::new(src_dir).is_dir() {
         return Err(ZipError::FileNotFound);
     }
@@ -247,11 +763,11 @@ fn doit(src_dir: &str, dst_file: &str) -> zip::result::ZipResult<()> {
 
     let walkdir = WalkDir::new(src_dir);
     let it = walkdir.into_iter().filter_entry(|entry: &DirEntry| {
-        let filename = entry.file_name().to_string_lossy();
-        filename != ".token" && filename != ".expiration"
+        let relative = entry.path().strip_prefix(src_dir).unwrap_or(entry.path()).to_string_lossy().replace('\\', "/");
+        !is_reserved(&entry.file_name().to_string_lossy()) && !meta.get(&relative).is_some_and(|meta| meta.quarantined)
     });
 
-    zip_dir(&mut it.filter_map(|e| e.ok()), src_dir, file)?;
+    zip_dir(&mut it.filter_map(|e| e.ok()), src_dir, meta, file)?;
 
     Ok(())
 }
@@ -268,8 +784,17 @@ pub async fn get_info(req: HttpRequest) -> Result<HttpResponse, Error> {
     let token_opt = req.headers().get("token");
     let is_owner = token_opt.is_some() && fs::read_to_string(format!("sessions/{session_id}/.token")).is_ok_and(|t| {token_opt.unwrap().to_str().unwrap() == t});
     let exp = fs::read_to_string(format!("sessions/{session_id}/.expiration"))?;
+    // With `checksums: true` every line carries the SHA-256 between size and name, `-` if unknown
+    let checksums = req.headers().get("checksums").is_some_and(|v| v == "true");
+    let meta = meta::load(&session_id);
     let files_and_sizes: Vec<String> = list_files_with_sizes(format!("sessions/{session_id}"))?.iter()
-        .map(|x| {format!("{} {}", x.1, x.0)}).collect();
+        .map(|x| match checksums {
+            true => {
+                let sha256 = meta.get(&x.0).and_then(|meta| meta.sha256.as_deref()).unwrap_or("-");
+                format!("{} {} {}", x.1, sha256, x.0)
+            }
+            false => format!("{} {}", x.1, x.0),
+        }).collect();
     let mut resp = HttpResponse::Ok().body(files_and_sizes.join("\n"));
     let headers = resp.headers_mut();
     headers.insert("expiration".parse().unwrap(), HeaderValue::from_str(&exp.to_string())?);
@@ -277,31 +802,264 @@ pub async fn get_info(req: HttpRequest) -> Result<HttpResponse, Error> {
     Ok(resp)
 }
 
+pub async fn sign_link(req: HttpRequest, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let session_id = path.into_inner();
+    if !is_owner_action(&req, &session_id) {
+        return Ok(HttpResponse::Forbidden().body("Invalid auth token, missing CSRF token or non existent session"))
+    }
+    let file = req.headers().get("file").map(|f| f.to_str().map(|f| f.to_string())).transpose()
+        .map_err(actix_web::error::ErrorBadRequest)?;
+    if file.as_ref().is_some_and(|file| session_file(&session_id, file).is_none()) {
+        return Ok(HttpResponse::NotFound().body("Non existent file within session"))
+    }
+    let lifetime = match req.headers().get("expires-in") {
+        Some(v) => match v.to_str().ok().and_then(|v| v.parse::<u64>().ok()) {
+            Some(lifetime) => lifetime,
+            None => return Ok(HttpResponse::BadRequest().body("Key is not a u64, expires-in")),
+        },
+        None => DEFAULT_LINK_LIFETIME,
+    };
+    let session_expiration = get_expiration_time(format!("sessions/{session_id}"))?.unwrap_or(0);
+    let expires = now().saturating_add(lifetime).min(session_expiration);
+    let ip = if req.headers().get("bind-ip").is_some_and(|v| v == "true") {
+        match client_ip(&req) {
+            Some(ip) => Some(ip),
+            None => return Ok(HttpResponse::BadRequest().body("Could not determine client address for bind-ip")),
+        }
+    } else { None };
+
+    let url = signed_url(&LinkScope { session: &session_id, file: file.as_deref(), expires, ip: ip.as_deref() });
+    Ok(HttpResponse::Ok()
+        .append_header(("expiration".to_string(), expires.to_string()))
+        .body(url))
+}
+
 pub async fn download_file(req: HttpRequest, path: web::Path<(String, String)>) -> Result<HttpResponse, Error> {
     let (session_id, filename) = path.into_inner();
-    let path = format!("sessions/{session_id}/{filename}");
-    if !fs::exists(&path)? {
+    let Some(path) = session_file(&session_id, &filename) else {
         return Ok(HttpResponse::NotFound().body("Non existent session or file within session"))
+    };
+    if let Some(res) = authorize_download(&req, &session_id, Some(&filename)) {
+        return Ok(res)
+    }
+    let file_meta = meta::get(&session_id, &filename);
+    let content_type = match &file_meta {
+        Some(meta) => meta.content_type.clone(),
+        None => mime_guess::from_path(&filename).first_or_octet_stream().to_string(),
+    };
+    let compressed = file_meta.as_ref().is_some_and(|meta| meta.compressed);
+    let original_size = file_meta.as_ref().and_then(|meta| meta.original_size);
+    // Compressed files go out as stored to clients that can decode them
+    let encoded = compressed && compression::accepts_zstd(&req);
+    let mut builder = HttpResponse::Ok();
+    builder
+        .content_type(content_type)
+        .append_header(("Content-Disposition", format!("attachment; filename=\"{}\"", leaf_name(&filename))));
+    // The digests describe the original bytes, not the zstd encoded ones
+    if let Some(sha256) = file_meta.and_then(|meta| meta.sha256).filter(|_| !encoded) {
+        let digests = checksum::structured_sha256(&sha256).zip(checksum::legacy_sha256(&sha256));
+        if let Some((repr_digest, digest)) = digests {
+            builder.append_header(("Repr-Digest", repr_digest)).append_header(("Digest", digest));
+        }
+    }
+    if compressed {
+        builder.append_header((VARY, "Accept-Encoding"));
+        if encoded {
+            builder.insert_header(ContentEncoding::Zstd);
+            return Ok(builder.body(compression::stream(Path::new(&path)).await?))
+        }
+        return decoded_response(&req, builder, Path::new(&path), original_size).await
     }
     match NamedFile::open(&path) {
         Ok(named_file) => {
-            let response = named_file.prefer_utf8(true).use_last_modified(true).into_response(&req);
-            Ok(HttpResponse::Ok()
-                .append_header(("Content-Disposition", format!("attachment; filename=\"{}\"", filename)))
-                .body(response.into_body()))
+            // The file response keeps its status and range headers, the headers set above replace its own
+            let mut response = named_file.prefer_utf8(true).use_last_modified(true).into_response(&req);
+            let headers = builder.finish();
+            for (name, value) in headers.headers() {
+                response.headers_mut().insert(name.clone(), value.clone());
+            }
+            Ok(response)
         }
         Err(_) => Ok(HttpResponse::NotFound().body("File not found")),
     }
 }
 
+/// Serves a compressed file decoded. With the original size known the length is sent and
+/// a single byte range is honoured, the decoder still has to run through the bytes before it.
+async fn decoded_response(req: &HttpRequest, mut builder: HttpResponseBuilder, path: &Path, size: Option<u64>) -> Result<HttpResponse, Error> {
+    let Some(size) = size else {
+        return Ok(builder.body(compression::stream_decoded(path, 0, None).await?))
+    };
+    builder.insert_header((ACCEPT_RANGES, "bytes"));
+    let range = req.headers().get(RANGE).and_then(|range| range.to_str().ok());
+    let (start, length) = match range.map(|range| HttpRange::parse(range, size)) {
+        None => (0, size),
+        Some(Ok(ranges)) => match ranges.first() {
+            Some(range) => {
+                let end = range.start + range.length - 1;
+                builder.status(StatusCode::PARTIAL_CONTENT).insert_header((CONTENT_RANGE, format!("bytes {}-{end}/{size}", range.start)));
+                (range.start, range.length)
+            }
+            None => (0, size),
+        },
+        Some(Err(_)) => return Ok(HttpResponse::RangeNotSatisfiable().insert_header((CONTENT_RANGE, format!("bytes */{size}"))).finish()),
+    };
+    Ok(builder.body(compression::stream_decoded(path, start, Some(length)).await?))
+}
+
+/// How the session page shows a preview
+#[derive(Clone, Copy)]
+pub enum PreviewKind {
+    Image,
+    Audio,
+    Video,
+    /// Text and PDFs, shown in a frame
+    Document,
+}
+
+impl PreviewKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            PreviewKind::Image => "image",
+            PreviewKind::Audio => "audio",
+            PreviewKind::Video => "video",
+            PreviewKind::Document => "document",
+        }
+    }
+}
+
+/// Text, markup, scripts and source code, judged by the file name
+pub fn is_textual(filename: &str) -> bool {
+    let guessed = mime_guess::from_path(filename).first_or_octet_stream();
+    let extension = Path::new(filename).extension().map(|e| e.to_string_lossy().to_ascii_lowercase());
+    guessed.type_() == mime::TEXT
+        || [mime::JSON, mime::JAVASCRIPT, mime::XML].contains(&guessed.subtype())
+        || extension.is_some_and(|e| TEXT_EXTENSIONS.contains(&e.as_str()))
+}
+
+/// The type `/f/preview` serves a file as, `None` if it is not on the allowlist.
+/// Everything textual, markup and scripts included, is sent as plain text. A type detected
+/// from the content wins over the name, the name only decides for undetected binary files.
+pub fn preview_type(filename: &str, detected: Option<&FileMeta>) -> Option<(Mime, PreviewKind)> {
+    let (mime, textual) = match detected {
+        Some(meta) if meta.is_text() => return Some((mime::TEXT_PLAIN_UTF_8, PreviewKind::Document)),
+        Some(meta) => match meta.content_type.parse::<Mime>() {
+            Ok(mime) if mime != mime::APPLICATION_OCTET_STREAM => (mime, false),
+            _ => (mime_guess::from_path(filename).first_or_octet_stream(), false),
+        },
+        None => (mime_guess::from_path(filename).first_or_octet_stream(), is_textual(filename)),
+    };
+    match (mime.type_(), mime.subtype().as_str()) {
+        (mime::IMAGE, "png" | "jpeg" | "gif" | "webp" | "avif" | "bmp") => Some((mime, PreviewKind::Image)),
+        (mime::AUDIO, _) => Some((mime, PreviewKind::Audio)),
+        (mime::VIDEO, _) => Some((mime, PreviewKind::Video)),
+        (mime::APPLICATION, "pdf") => Some((mime, PreviewKind::Document)),
+        _ if textual => Some((mime::TEXT_PLAIN_UTF_8, PreviewKind::Document)),
+        _ => None,
+    }
+}
+
+/// Serves allowlisted files inline, with range support for media players
+pub async fn preview_file(req: HttpRequest, path: web::Path<(String, String)>) -> Result<HttpResponse, Error> {
+    let (session_id, filename) = path.into_inner();
+    let Some(path) = session_file(&session_id, &filename) else {
+        return Ok(HttpResponse::NotFound().body("Non existent session or file within session"))
+    };
+    if let Some(res) = authorize_download(&req, &session_id, Some(&filename)) {
+        return Ok(res)
+    }
+    let file_meta = meta::get(&session_id, &filename);
+    let Some((mime, _)) = preview_type(&filename, file_meta.as_ref()) else {
+        return Ok(HttpResponse::UnsupportedMediaType().body("This type of file has no preview"))
+    };
+    let disposition = ContentDisposition {
+        disposition: DispositionType::Inline,
+        parameters: vec![DispositionParam::Filename(leaf_name(&filename).to_string())],
+    };
+    if let Some(meta) = file_meta.filter(|meta| meta.compressed) {
+        let mut builder = HttpResponse::Ok();
+        builder.content_type(mime).insert_header(disposition);
+        return decoded_response(&req, builder, Path::new(&path), meta.original_size).await
+    }
+    let file = NamedFile::open(&path)?
+        .set_content_type(mime)
+        .set_content_disposition(disposition)
+        .use_last_modified(true);
+    Ok(file.into_response(&req))
+}
+
+/// Lists the contents of an uploaded archive without extracting it
+pub async fn browse_archive(req: HttpRequest, path: web::Path<(String, String)>) -> Result<HttpResponse, Error> {
+    let (session_id, filename) = path.into_inner();
+    let (Some(path), Some(kind)) = (session_file(&session_id, &filename), ArchiveKind::of(&filename)) else {
+        return Ok(HttpResponse::NotFound().body("Non existent archive within session"))
+    };
+    if let Some(res) = authorize_page(&req, &session_id, Some(&filename)) {
+        return Ok(res)
+    }
+    match web::block(move || archive::list(Path::new(&path), kind)).await? {
+        Ok(entries) => Ok(HttpResponse::Ok().content_type(ContentType::html()).body(load_archive(&session_id, &filename, entries))),
+        Err(ArchiveError::Io(err)) => Err(err.into()),
+        Err(err) => Ok(HttpResponse::BadRequest().body(err.message())),
+    }
+}
+
+/// Text files as page, highlighted source or rendered Markdown
+pub async fn view_file(req: HttpRequest, path: web::Path<(String, String)>) -> Result<HttpResponse, Error> {
+    let (session_id, filename) = path.into_inner();
+    let detected = meta::get(&session_id, &filename);
+    let textual = detected.as_ref().map_or_else(|| is_textual(&filename), FileMeta::is_text);
+    let Some(path) = session_file(&session_id, &filename).filter(|_| textual) else {
+        return Ok(HttpResponse::NotFound().body("Non existent text file within session"))
+    };
+    if let Some(res) = authorize_page(&req, &session_id, Some(&filename)) {
+        return Ok(res)
+    }
+    // The download link on the page reuses a valid signature
+    let link_query = is_signed_only(&session_id).then(|| req.query_string().to_string());
+    let name = filename.clone();
+    let compressed = detected.is_some_and(|meta| meta.compressed);
+    let view = web::block(move || textview::render(Path::new(&path), &name, compressed)).await??;
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(load_view(&session_id, &filename, view, link_query)))
+}
+
+/// Recomputes the SHA-256 of a stored file and compares it with the `sha256` header
+pub async fn verify_file(req: HttpRequest, path: web::Path<(String, String)>) -> Result<HttpResponse, Error> {
+    let (session_id, filename) = path.into_inner();
+    let Some(path) = session_file(&session_id, &filename) else {
+        return Ok(HttpResponse::NotFound().body("Non existent session or file within session"))
+    };
+    if let Some(res) = authorize_page(&req, &session_id, Some(&filename)) {
+        return Ok(res)
+    }
+    let Some(expected) = req.headers().get("sha256").and_then(|v| v.to_str().ok()).map(|v| v.trim().to_ascii_lowercase()) else {
+        return Ok(HttpResponse::BadRequest().body("Key not found, sha256"))
+    };
+    let file_meta = meta::get(&session_id, &filename);
+    let compressed = file_meta.as_ref().is_some_and(|meta| meta.compressed);
+    let actual = web::block(move || checksum::of_reader(compression::open(Path::new(&path), compressed)?)).await??.sha256;
+    let recorded = file_meta.and_then(|meta| meta.sha256);
+    if recorded.is_some_and(|recorded| recorded != actual) {
+        return Ok(HttpResponse::InternalServerError().body("Stored file no longer matches the checksum recorded at upload"))
+    }
+    if expected == actual {
+        Ok(HttpResponse::Ok().body(actual))
+    } else {
+        Ok(HttpResponse::Conflict().body(format!("Checksum mismatch, the stored file has {actual}")))
+    }
+}
+
 pub async fn download_zip(req: HttpRequest, path: web::Path<String>) -> Result<HttpResponse, Error> {
     let session_id = path.into_inner();
     let path = format!("sessions/{session_id}");
     if !fs::exists(&path)? {
         return Ok(HttpResponse::NotFound().body("Non existent session or file within session"))
     }
+    if let Some(res) = authorize_download(&req, &session_id, None) {
+        return Ok(res)
+    }
     let filename = temp_dir().join(random_str(50)).to_str().unwrap().to_string();
-    doit(&path, &filename).expect("Failed to save zip");
+    doit(&path, &filename, &meta::load(&session_id)).expect("Failed to save zip");
     match NamedFile::open(&filename) {
         Ok(named_file) => {
             let mut response = named_file.use_last_modified(false).prefer_utf8(true).into_response(&req);
@@ -324,30 +1082,36 @@ pub async fn fup_ld_index(req: HttpRequest) -> Result<HttpResponse, Error> {
             return Ok(Redirect::to(format!("/f/session/{}", cookie.name())).respond_to(&req).map_into_boxed_body())
         }
     }
-    Ok(HttpResponse::Ok().body(get_fileupload_index()?))
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(get_fileupload_index()?))
 }
 
 pub async fn load_sesh(req: HttpRequest, path: web::Path<String>) -> Result<HttpResponse, Error> {
     let session_id = path.into_inner();
     let exists = fs::exists(format!("sessions/{session_id}"))?;
-    let cookie_x = req.cookie(&session_id);
 
     if !exists {
         return Ok(HttpResponse::NotFound().finish())
     }
 
-    let owner = if cookie_x.is_some() {
-        let token = cookie_x.unwrap().value().to_string();
-        if fs::read_to_string(format!("sessions/{session_id}/.token"))? == token {
-            Some(token)
-        } else {
-            None
+    if let Some(res) = abuse::refuse_disabled(&session_id) {
+        return Ok(res)
+    }
+    let owner = cookie_owner(&req, &session_id);
+
+    let expiration = get_expiration_time(format!("sessions/{session_id}"))?.expect("No expiration!!");
+    // Signed-only sessions are not browsable by ID alone; the owner gets short-lived links
+    let link_query = if is_signed_only(&session_id) {
+        if owner.is_none() {
+            return Ok(HttpResponse::Forbidden().finish())
         }
-    } else {None};
+        let expires = (now() + OWNER_LINK_LIFETIME).min(expiration);
+        Some(signed_query(&LinkScope { session: &session_id, file: None, expires, ip: None }))
+    } else { None };
 
     let files = list_files_with_sizes(format!("sessions/{session_id}"))?;
-    let expiration = get_expiration_time(format!("sessions/{session_id}"))?;
-    let html = load_all(session_id, expiration.expect("No expiration!!"), files, owner);
+    let csrf = owner.map(|token| csrf_token(&session_id, &token));
+    let meta = meta::load(&session_id);
+    let html = load_all(session_id, expiration, files, &meta, csrf, link_query);
 
-    Ok(HttpResponse::Ok().body(html))
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(html))
 }
\ No newline at end of file
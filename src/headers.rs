@@ -0,0 +1,82 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue, CONTENT_SECURITY_POLICY, REFERRER_POLICY, STRICT_TRANSPORT_SECURITY, X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS};
+use actix_web::middleware::Next;
+use actix_web::Error;
+use lazy_static::lazy_static;
+use crate::config::CONFIG;
+use crate::get_hostname;
+use crate::origin::content_domain;
+
+/// Routes answering with bytes uploaded by users
+pub const USER_CONTENT_PREFIXES: [&str; 3] = ["/f/download/", "/f/preview/", "/f/thumb/"];
+/// User content the session page embeds
+const PREVIEW_PREFIX: &str = "/f/preview/";
+
+lazy_static! {
+    /// The page policy, also allowing embedded previews from the content domain
+    static ref PAGE_CSP: Option<String> = CONFIG.security_headers.content_security_policy.as_ref().map(|csp| {
+        match content_domain() {
+            Some(domain) => allow_source(csp, &["img-src", "media-src", "frame-src"], &format!("https://{domain}")),
+            None => csp.clone(),
+        }
+    });
+    /// The user content policy for previews, which the main host may frame
+    static ref PREVIEW_CSP: Option<String> = CONFIG.security_headers.user_content_security_policy.as_ref()
+        .map(|csp| format!("{csp}; frame-ancestors 'self' https://{}", get_hostname()));
+}
+
+/// Adds `source` to the listed directives that the policy contains
+fn allow_source(csp: &str, directives: &[&str], source: &str) -> String {
+    csp.split(';')
+        .map(|directive| directive.trim())
+        .map(|directive| match directive.split_whitespace().next() {
+            Some(name) if directives.contains(&name) => format!("{directive} {source}"),
+            _ => directive.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+pub async fn security_headers(req: ServiceRequest, next: Next<impl MessageBody>) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let config = &CONFIG.security_headers;
+    let user_content = USER_CONTENT_PREFIXES.iter().any(|prefix| req.path().starts_with(prefix));
+    let preview = req.path().starts_with(PREVIEW_PREFIX);
+    let mut res = next.call(req).await?;
+    if !config.enabled {
+        return Ok(res)
+    }
+
+    // Error pages on user content routes are still our own pages
+    let user_content = user_content && res.status().is_success();
+    let preview = preview && user_content;
+    let csp = match (user_content, preview) {
+        (true, true) => &*PREVIEW_CSP,
+        (true, false) => &config.user_content_security_policy,
+        _ => &*PAGE_CSP,
+    };
+    // frame-ancestors of the preview policy takes over
+    let frame_options = if preview { None } else { config.frame_options.as_deref() };
+    let headers = [
+        (X_CONTENT_TYPE_OPTIONS, Some("nosniff")),
+        (CONTENT_SECURITY_POLICY, csp.as_deref()),
+        (REFERRER_POLICY, config.referrer_policy.as_deref()),
+        (STRICT_TRANSPORT_SECURITY, config.strict_transport_security.as_deref()),
+        (X_FRAME_OPTIONS, frame_options),
+    ];
+    let response_headers = res.headers_mut();
+    for (name, value) in headers {
+        if let Some(value) = value {
+            insert_missing(response_headers, name, value);
+        }
+    }
+    Ok(res)
+}
+
+fn insert_missing(headers: &mut actix_web::http::header::HeaderMap, name: HeaderName, value: &str) {
+    if !headers.contains_key(&name) {
+        if let Ok(value) = HeaderValue::from_str(value) {
+            headers.insert(name, value);
+        }
+    }
+}
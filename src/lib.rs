@@ -0,0 +1,243 @@
+mod water;
+mod fileupload;
+mod signing;
+mod config;
+mod ratelimit;
+mod quota;
+mod headers;
+mod origin;
+mod api;
+mod apikeys;
+mod archive;
+mod thumbnail;
+mod textview;
+mod meta;
+mod filetype;
+mod checksum;
+mod blobstore;
+mod compression;
+mod scanner;
+mod blocklist;
+mod abuse;
+
+pub use abuse::password_cli as admin_password_cli;
+pub use apikeys::cli as apikey_cli;
+pub use blocklist::cli as blocklist_cli;
+pub use fileupload::parse_lifetime;
+
+extern crate walkdir;
+use actix_web::http::{Method, StatusCode};
+use actix_web::middleware::{from_fn, Compress, ErrorHandlerResponse, ErrorHandlers};
+use std::{fs, io};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use actix_web::{web, App, Error, HttpRequest, HttpResponse, HttpResponseBuilder};
+use rand::{distr::Alphanumeric, Rng};
+use std::string::ToString;
+use std::sync::Mutex;
+use actix_files::NamedFile;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{ContentType, RETRY_AFTER, WWW_AUTHENTICATE};
+use tokio::task;
+use lazy_static::lazy_static;
+use crate::thumbnail::thumbnail;
+use crate::fileupload::{browse_archive, delete, download_file, download_zip, fup_ld_index, get_info, is_entry_owner, load_sesh, preview_file, sign_link, upload, upload_raw, verify_file, view_file};
+use crate::config::CONFIG;
+use crate::headers::security_headers;
+use crate::origin::content_host_guard;
+use crate::quota::usage;
+use crate::ratelimit::rate_limit;
+use crate::water::{get_article, get_articles, get_index, get_script, get_style, load_err_html};
+
+macro_rules! error_handler_many {
+    ($handler:ident, [$($variant:ident),*]) => {
+        ErrorHandlers::new()
+            $(.handler(StatusCode::$variant, $handler))+
+    }
+}
+
+const DEFAULT_RND_STR_LEN: usize = 15;
+lazy_static! {
+    static ref HOSTNAME: Mutex<String> = {
+        let host = "localhost:8080".to_string();
+        Mutex::new(host)
+    };
+}
+
+async fn load_css(_req: HttpRequest) -> Result<HttpResponse, Error> {
+    Ok(HttpResponse::Ok().content_type("text/css; charset=utf-8").body(get_style()?))
+}
+
+async fn load_highlight_css() -> HttpResponse {
+    HttpResponse::Ok().content_type("text/css; charset=utf-8").body(textview::HIGHLIGHT_CSS.as_str())
+}
+
+async fn load_script(path: web::Path<String>) -> Result<HttpResponse, Error> {
+    match get_script(&path.into_inner())? {
+        Some(script) => Ok(HttpResponse::Ok().content_type("text/javascript; charset=utf-8").body(script)),
+        None => Err(actix_web::error::ErrorNotFound("Resource not found")),
+    }
+}
+
+#[actix_web::get("/")]
+async fn load_index(_req: HttpRequest) -> Result<HttpResponse, Error> {
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(get_index()?))
+}
+
+#[allow(clippy::unnecessary_unwrap)]
+async fn load_article(_req: HttpRequest, path: web::Path<String>) -> Result<HttpResponse, Error> {
+    let article = get_article(path.into_inner())?;
+    if article.is_none() {
+        Err(actix_web::error::ErrorNotFound("Resource not found"))
+    } else {
+        Ok(HttpResponse::Ok().content_type(ContentType::html()).body(article.unwrap()))
+    }
+}
+
+async fn load_articles(_req: HttpRequest) -> Result<HttpResponse, Error> {
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(get_articles()?))
+}
+
+async fn load_resource(path: web::Path<String>) -> Result<NamedFile, Error> {
+    let path: PathBuf = Path::new("resources").join(path.into_inner());
+    if path.exists() {
+        Ok(NamedFile::open(path)?)
+    } else {
+        Err(actix_web::error::ErrorNotFound("Resource not found"))
+    }
+}
+
+fn random_str(length: usize) -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+pub fn get_hostname() -> String {
+    HOSTNAME.lock().unwrap().to_string()
+}
+
+fn get_domain() -> String {
+    let hst = HOSTNAME.lock().unwrap();
+    hst.split_once(':').unwrap_or((hst.as_str(), "")).0.to_string()
+}
+
+/// The address of the client, taking `X-Forwarded-For` / `X-Real-IP` into account when the peer is a trusted proxy
+pub fn client_ip(req: &HttpRequest) -> Option<String> {
+    let peer = req.peer_addr()?.ip();
+    if !CONFIG.trusted_proxies.contains(&peer) {
+        return Some(peer.to_string())
+    }
+    let forwarded = req.headers().get("x-forwarded-for").and_then(|h| h.to_str().ok()).unwrap_or("");
+    let from_forwarded = forwarded
+        .rsplit(',')
+        .filter_map(|ip| ip.trim().parse::<IpAddr>().ok())
+        .find(|ip| !CONFIG.trusted_proxies.contains(ip));
+    let from_real_ip = req.headers().get("x-real-ip")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|ip| ip.trim().parse::<IpAddr>().ok());
+    Some(from_forwarded.or(from_real_ip).unwrap_or(peer).to_string())
+}
+
+pub fn mod_host() {
+    let mut mut_host = HOSTNAME.lock().unwrap();
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() > 1 {
+        *mut_host = args[1].to_string();
+    } else if fs::exists(".host").expect("FAILED TO FIND OUT IF .host EXISTS") {
+        *mut_host = fs::read_to_string(".host").expect("FAILED TO READ .host")
+    }
+}
+
+#[allow(clippy::missing_errors_doc)]
+pub fn render_error<B>(res: ServiceResponse<B>) -> Result<ErrorHandlerResponse<B>, Error> {
+    // The JSON API and raw (curl) uploads keep their machine-readable error bodies
+    if res.request().path().starts_with("/api/") || res.request().method() == Method::PUT {
+        return Ok(ErrorHandlerResponse::Response(res.map_into_left_body()))
+    }
+    let status = res.status();
+    let retry_after = res.headers().get(RETRY_AFTER).cloned();
+    let authenticate = res.headers().get(WWW_AUTHENTICATE).cloned();
+    let request = res.into_parts().0;
+
+    let mut builder = HttpResponseBuilder::new(status);
+    builder.insert_header(ContentType::html());
+    if let Some(retry_after) = retry_after {
+        builder.insert_header((RETRY_AFTER, retry_after));
+    }
+    if let Some(authenticate) = authenticate {
+        builder.insert_header((WWW_AUTHENTICATE, authenticate));
+    }
+    let new_response = builder.body(load_err_html(status.as_u16())?);
+
+    Ok(ErrorHandlerResponse::Response(
+        ServiceResponse::new(request, new_response).map_into_right_body(),
+    ))
+}
+
+/// Creates the working directories and starts the background jobs, needs a running actix system
+pub fn prepare() -> io::Result<()> {
+    fs::create_dir_all("sessions")?;
+    fs::create_dir_all(blobstore::BLOB_DIR)?;
+    task::spawn(fileupload::background_cleanup("sessions"));
+    task::spawn(blobstore::background_gc());
+    task::spawn(quota::background_measure());
+    task::spawn(ratelimit::background_prune());
+    fs::create_dir_all("articles")?;
+    fs::create_dir_all("resources")?;
+    Ok(())
+}
+
+pub fn app() -> App<impl ServiceFactory<ServiceRequest, Config = (), Response = ServiceResponse<impl MessageBody>, Error = Error, InitError = ()>> {
+    App::new()
+    .wrap(from_fn(rate_limit))
+    .wrap(from_fn(content_host_guard))
+    .wrap(error_handler_many!(render_error, [BAD_REQUEST, UNAUTHORIZED, FORBIDDEN,
+        NOT_FOUND, METHOD_NOT_ALLOWED, NOT_ACCEPTABLE, REQUEST_TIMEOUT, GONE,
+        LENGTH_REQUIRED, PAYLOAD_TOO_LARGE, INSUFFICIENT_STORAGE, URI_TOO_LONG, UNSUPPORTED_MEDIA_TYPE,
+        RANGE_NOT_SATISFIABLE, IM_A_TEAPOT, TOO_MANY_REQUESTS,
+        REQUEST_HEADER_FIELDS_TOO_LARGE, MISDIRECTED_REQUEST, UPGRADE_REQUIRED,
+        INTERNAL_SERVER_ERROR, NOT_IMPLEMENTED, SERVICE_UNAVAILABLE,
+        HTTP_VERSION_NOT_SUPPORTED]))
+    .wrap(from_fn(security_headers))
+    .service(web::scope(api::API_PREFIX)
+        .wrap(CONFIG.cors.api.build())
+        .configure(api::configure))
+    .service(web::scope("/f")
+        .wrap(CONFIG.cors.api.build())
+        .route("/get-info", web::get().to(get_info))
+        .route("/upload", web::post().to(upload))
+        .route("/is-owner", web::post().to(is_entry_owner))
+        .route("/delete/{session}", web::post().to(delete))
+        .route("/sign/{session}", web::post().to(sign_link))
+        .route("/usage", web::get().to(usage))
+        .route("/download/{session}", web::get().to(download_zip))
+        .route("/download/{session}/{filename:.*}", web::get().to(download_file))
+        .route("/preview/{session}/{filename:.*}", web::get().to(preview_file))
+        .route("/thumb/{session}/{filename:.*}", web::get().to(thumbnail))
+        .service(web::resource("/browse/{session}/{filename:.*}").wrap(Compress::default()).get(browse_archive))
+        .service(web::resource("/view/{session}/{filename:.*}").wrap(Compress::default()).get(view_file))
+        .route("/verify/{session}/{filename:.*}", web::get().to(verify_file))
+        .route("/report/{session}", web::post().to(abuse::report))
+        .service(web::resource("/session/{session}").wrap(Compress::default()).get(load_sesh))
+        .service(web::resource("/index").wrap(Compress::default()).get(fup_ld_index))
+        .route("/", web::put().to(upload_raw))
+        .route("/{filename}", web::put().to(upload_raw)))
+    // Not compressed, the pages put the CSRF token next to report reasons anyone can write
+    .service(web::scope("/admin")
+        .route("/reports", web::get().to(abuse::review))
+        .route("/reports/{session}/{action}", web::post().to(abuse::resolve)))
+    .service(web::scope("")
+        .wrap(CONFIG.cors.pages.build())
+        .wrap(Compress::default())
+        .service(load_index)
+        .route("/r/style.css", web::get().to(load_css))
+        .route("/r/highlight.css", web::get().to(load_highlight_css))
+        .route("/r/js/{script}", web::get().to(load_script))
+        .route("/r/{resource}", web::get().to(load_resource))
+        .route("/a/{articles}", web::get().to(load_article))
+        .route("/articles", web::get().to(load_articles)))
+}
@@ -0,0 +1,69 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::checksum::Digests;
+use crate::scanner::ScanStatus;
+
+/// Metadata of all files of a session, JSON keyed by the `/` separated path
+pub const META_FILE: &str = ".meta";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FileMeta {
+    /// Detected from the first bytes, not the name
+    pub content_type: String,
+    /// Stored and listed, but never served
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub quarantined: bool,
+    /// Hex digests of the content as uploaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blake3: Option<String>,
+    /// Stored zstd compressed
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub compressed: bool,
+    /// Size of the original content of compressed files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_size: Option<u64>,
+    /// Only with a malware scanner configured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scan: Option<ScanStatus>,
+}
+
+impl FileMeta {
+    pub fn with_digests(self, digests: Digests) -> Self {
+        FileMeta { sha256: Some(digests.sha256), blake3: digests.blake3, ..self }
+    }
+
+    pub fn is_text(&self) -> bool {
+        self.content_type.starts_with("text/")
+    }
+}
+
+pub type SessionMeta = BTreeMap<String, FileMeta>;
+
+/// Empty for sessions created before metadata was recorded
+pub fn load(session_id: &str) -> SessionMeta {
+    load_in(&Path::new("sessions").join(session_id))
+}
+
+/// Metadata of the session stored in `dir`
+pub fn load_in(dir: &Path) -> SessionMeta {
+    fs::read(dir.join(META_FILE)).ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+pub fn save(session_id: &str, meta: &SessionMeta) -> io::Result<()> {
+    fs::write(format!("sessions/{session_id}/{META_FILE}"), serde_json::to_vec(meta)?)
+}
+
+pub fn get(session_id: &str, file: &str) -> Option<FileMeta> {
+    load(session_id).remove(file)
+}
+
+pub fn is_quarantined(session_id: &str, file: &str) -> bool {
+    get(session_id, file).is_some_and(|meta| meta.quarantined)
+}
@@ -0,0 +1,48 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HOST, LOCATION};
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpRequest, HttpResponse};
+use crate::config::CONFIG;
+use crate::headers::USER_CONTENT_PREFIXES;
+use crate::signing::{signed_query, LinkScope};
+
+pub fn content_domain() -> Option<&'static str> {
+    CONFIG.content_domain.as_deref()
+}
+
+pub fn is_content_host(req: &HttpRequest) -> bool {
+    content_domain().is_some_and(|domain| request_host(req).eq_ignore_ascii_case(domain))
+}
+
+/// The host the client asked for. `Forwarded` / `X-Forwarded-Host` only count when a trusted proxy sent them,
+/// anyone else could pass for the content domain.
+fn request_host(req: &HttpRequest) -> String {
+    if req.peer_addr().is_some_and(|peer| CONFIG.trusted_proxies.contains(&peer.ip())) {
+        return req.connection_info().host().to_string()
+    }
+    req.headers().get(HOST).and_then(|host| host.to_str().ok())
+        .or_else(|| req.uri().authority().map(|authority| authority.as_str()))
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Sends the client to the same path on the content domain, with a short-lived signature for `file`
+/// (or the whole session if `None`) attached.
+pub fn redirect(req: &HttpRequest, session: &str, file: Option<&str>) -> HttpResponse {
+    let domain = content_domain().expect("redirect without content domain");
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let query = signed_query(&LinkScope { session, file, expires: now + CONFIG.content_link_secs, ip: None });
+    HttpResponse::TemporaryRedirect()
+        .insert_header((LOCATION, format!("https://{domain}{}?{query}", req.uri().path())))
+        .finish()
+}
+
+/// The content domain answers nothing but user content
+pub async fn content_host_guard(req: ServiceRequest, next: Next<impl MessageBody + 'static>) -> Result<ServiceResponse<BoxBody>, Error> {
+    if is_content_host(req.request()) && !USER_CONTENT_PREFIXES.iter().any(|prefix| req.path().starts_with(prefix)) {
+        return Ok(req.into_response(HttpResponse::NotFound().finish()))
+    }
+    Ok(next.call(req).await?.map_into_boxed_body())
+}
@@ -0,0 +1,254 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use actix_web::http::header::CONTENT_LENGTH;
+use actix_web::http::StatusCode;
+use actix_web::{web, Error, HttpRequest, HttpResponse};
+use lazy_static::lazy_static;
+use tokio::time::sleep;
+use walkdir::WalkDir;
+use crate::apikeys::{ApiKey, KeyError};
+use crate::blobstore::{file_id, BLOB_DIR};
+use crate::{apikeys, client_ip};
+use crate::config::CONFIG;
+
+/// How often the bytes on disk are measured again, in between recorded uploads are added to the last measurement
+const MEASURE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+lazy_static! {
+    /// Uploaded bytes per client key as `(timestamp, bytes)`, oldest first
+    static ref LEDGER: Mutex<HashMap<String, VecDeque<(u64, u64)>>> = Mutex::new(HashMap::new());
+    /// Bytes held by uploads in progress, counted as used until the upload is recorded or dropped
+    static ref RESERVED: Mutex<Reserved> = Mutex::new(Reserved::default());
+    /// Bytes on disk, `None` until first measured
+    static ref STORED: Mutex<Option<u64>> = Mutex::new(None);
+}
+
+#[derive(Default)]
+struct Reserved {
+    total: u64,
+    clients: HashMap<String, u64>,
+}
+
+pub enum QuotaExceeded {
+    Storage,
+    Client,
+}
+
+impl QuotaExceeded {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            QuotaExceeded::Storage => StatusCode::INSUFFICIENT_STORAGE,
+            QuotaExceeded::Client => StatusCode::PAYLOAD_TOO_LARGE,
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            QuotaExceeded::Storage => "Storage quota of the server exceeded",
+            QuotaExceeded::Client => "Upload quota exceeded, try again later",
+        }
+    }
+}
+
+/// How much a single upload may still store. Checked bytes stay reserved for the upload,
+/// so concurrent uploads cannot together exceed a quota.
+pub struct Allowance {
+    keys: Vec<(String, Option<u64>)>,
+    /// Bytes this upload holds in `RESERVED`
+    reserved: u64,
+}
+
+impl Allowance {
+    /// `(storage, client)` bytes that may be stored, besides what other uploads reserved
+    fn available(&self, reserved: &Reserved) -> (u64, u64) {
+        let storage = match CONFIG.quota.total_bytes {
+            Some(limit) => limit.saturating_sub(stored().unwrap_or(u64::MAX)).saturating_sub(reserved.total - self.reserved),
+            None => u64::MAX,
+        };
+        let client = self.keys.iter()
+            .filter_map(|(key, limit)| {
+                let others = reserved.clients.get(key).map_or(0, |bytes| bytes - self.reserved);
+                limit.map(|limit| limit.saturating_sub(window_usage(key)).saturating_sub(others))
+            })
+            .min()
+            .unwrap_or(u64::MAX);
+        (storage, client)
+    }
+
+    /// Reserves `bytes` for the upload if they fit
+    pub fn check(&mut self, bytes: u64) -> Result<(), QuotaExceeded> {
+        if bytes <= self.reserved {
+            return Ok(())
+        }
+        let mut reserved = RESERVED.lock().unwrap();
+        let (storage, client) = self.available(&reserved);
+        if bytes > storage {
+            return Err(QuotaExceeded::Storage)
+        } else if bytes > client {
+            return Err(QuotaExceeded::Client)
+        }
+        let added = bytes - self.reserved;
+        reserved.total += added;
+        for (key, _) in &self.keys {
+            *reserved.clients.entry(key.clone()).or_default() += added;
+        }
+        self.reserved = bytes;
+        Ok(())
+    }
+
+    /// Bytes that pass `check`
+    pub fn remaining(&self) -> u64 {
+        let (storage, client) = self.available(&RESERVED.lock().unwrap());
+        storage.min(client)
+    }
+
+    /// Checks the declared `Content-Length`, before anything is read
+    pub fn check_request(&mut self, req: &HttpRequest) -> Result<(), QuotaExceeded> {
+        let length = req.headers().get(CONTENT_LENGTH)
+            .and_then(|l| l.to_str().ok())
+            .and_then(|l| l.parse::<u64>().ok())
+            .unwrap_or(0);
+        self.check(length)
+    }
+
+    /// Settles a finished upload, its bytes count as stored and against the client windows instead of the reservation
+    pub fn record(&mut self, bytes: u64) {
+        let now = now();
+        let oldest = now.saturating_sub(CONFIG.quota.window_secs);
+        {
+            let mut ledger = LEDGER.lock().unwrap();
+            ledger.retain(|_, entries| {
+                entries.retain(|(time, _)| *time >= oldest);
+                !entries.is_empty()
+            });
+            for (key, _) in &self.keys {
+                ledger.entry(key.clone()).or_default().push_back((now, bytes));
+            }
+        }
+        if let Some(stored) = STORED.lock().unwrap().as_mut() {
+            *stored += bytes;
+        }
+        self.release();
+    }
+
+    fn release(&mut self) {
+        if self.reserved == 0 {
+            return
+        }
+        let mut reserved = RESERVED.lock().unwrap();
+        reserved.total -= self.reserved;
+        for (key, _) in &self.keys {
+            if let Some(bytes) = reserved.clients.get_mut(key) {
+                *bytes -= self.reserved;
+                if *bytes == 0 {
+                    reserved.clients.remove(key);
+                }
+            }
+        }
+        self.reserved = 0;
+    }
+}
+
+/// Failed uploads give their reservation back
+impl Drop for Allowance {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+/// Keys uploads are accounted under, together with their limit
+fn client_keys(req: &HttpRequest, key: Option<&ApiKey>) -> Vec<(String, Option<u64>)> {
+    let mut keys = vec![(format!("ip:{}", client_ip(req).unwrap_or_default()), CONFIG.quota.per_ip_bytes)];
+    if let Some(key) = key {
+        keys.push((format!("key:{}", key.id), key.limits.quota_bytes.or(CONFIG.quota.per_key_bytes)));
+    }
+    keys
+}
+
+fn window_usage(key: &str) -> u64 {
+    let oldest = now().saturating_sub(CONFIG.quota.window_secs);
+    let mut ledger = LEDGER.lock().unwrap();
+    let Some(entries) = ledger.get_mut(key) else {
+        return 0
+    };
+    while entries.front().is_some_and(|(time, _)| *time < oldest) {
+        entries.pop_front();
+    }
+    let usage = entries.iter().map(|(_, bytes)| bytes).sum();
+    if entries.is_empty() {
+        ledger.remove(key);
+    }
+    usage
+}
+
+/// Bytes on disk below `sessions/` and the blob store, files linked several times count once
+pub fn storage_used() -> io::Result<u64> {
+    let mut total = 0;
+    let mut seen = HashSet::new();
+    for entry in WalkDir::new("sessions").into_iter().chain(WalkDir::new(BLOB_DIR)) {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            let metadata = entry.metadata()?;
+            if file_id(&metadata).is_none_or(|id| seen.insert(id)) {
+                total += metadata.len();
+            }
+        }
+    }
+    Ok(total)
+}
+
+/// Bytes on disk as last measured plus the uploads recorded since, measured on first use
+fn stored() -> io::Result<u64> {
+    let mut stored = STORED.lock().unwrap();
+    match *stored {
+        Some(bytes) => Ok(bytes),
+        None => Ok(*stored.insert(storage_used()?)),
+    }
+}
+
+/// Measures the bytes on disk again now and then, deleted sessions and blobs show up this way
+pub async fn background_measure() {
+    loop {
+        sleep(MEASURE_INTERVAL).await;
+        if CONFIG.quota.total_bytes.is_none() {
+            continue
+        }
+        match web::block(storage_used).await {
+            Ok(Ok(bytes)) => *STORED.lock().unwrap() = Some(bytes),
+            Ok(Err(e)) => eprintln!("Error measuring storage: {}", e),
+            Err(e) => eprintln!("Error measuring storage: {}", e),
+        }
+    }
+}
+
+pub fn allowance(req: &HttpRequest, key: Option<&ApiKey>) -> io::Result<Allowance> {
+    if CONFIG.quota.total_bytes.is_some() {
+        stored()?;
+    }
+    Ok(Allowance { keys: client_keys(req, key), reserved: 0 })
+}
+
+fn usage_line(name: &str, used: u64, limit: Option<u64>) -> String {
+    format!("{name} {used} {}", limit.map(|l| l.to_string()).unwrap_or("-".to_string()))
+}
+
+/// Reports `<scope> <used bytes> <limit or ->` lines for the whole server and the calling client
+pub async fn usage(req: HttpRequest) -> Result<HttpResponse, Error> {
+    let key = match apikeys::authenticate(&req) {
+        Ok(key) => key,
+        Err(KeyError::Invalid) => return Ok(HttpResponse::Unauthorized().body("Invalid API key")),
+        Err(KeyError::Io(err)) => return Err(err.into()),
+    };
+    let mut lines = vec![usage_line("storage", stored()?, CONFIG.quota.total_bytes)];
+    for (key, limit) in client_keys(&req, key.as_ref()) {
+        let scope = key.split_once(':').map(|(scope, _)| scope).unwrap_or(&key);
+        lines.push(usage_line(scope, window_usage(&key), limit));
+    }
+    Ok(HttpResponse::Ok().body(lines.join("\n")))
+}
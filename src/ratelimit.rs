@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::RETRY_AFTER;
+use actix_web::http::{Method, StatusCode};
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpRequest, HttpResponse, ResponseError};
+use lazy_static::lazy_static;
+use tokio::time::sleep;
+use crate::api::ApiError;
+use crate::{apikeys, client_ip};
+use crate::fileupload::{is_session_id, is_token};
+use crate::config::{BucketConfig, CONFIG};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum BucketKind {
+    Upload,
+    Metadata,
+    Download,
+    AuthFailure,
+}
+
+impl BucketKind {
+    fn config(self) -> BucketConfig {
+        let limits = &CONFIG.rate_limit;
+        match self {
+            BucketKind::Upload => limits.upload,
+            BucketKind::Metadata => limits.metadata,
+            BucketKind::Download => limits.download,
+            BucketKind::AuthFailure => limits.auth_failure,
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, config: BucketConfig) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * config.refill_per_sec).min(config.capacity);
+        self.updated = now;
+    }
+
+    /// Time until one token is available, zero if there already is one
+    fn wait_time(&self, config: BucketConfig) -> Duration {
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else if config.refill_per_sec <= 0.0 {
+            Duration::MAX
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) / config.refill_per_sec)
+        }
+    }
+}
+
+lazy_static! {
+    static ref BUCKETS: Mutex<HashMap<(BucketKind, String), Bucket>> = Mutex::new(HashMap::new());
+}
+
+/// Refills the bucket for `key`, then takes a token if `consume` is set.
+/// Returns how long the client has to wait if the bucket is empty.
+fn check(kind: BucketKind, key: &str, consume: bool) -> Result<(), Duration> {
+    let config = kind.config();
+    let mut buckets = BUCKETS.lock().unwrap();
+    let bucket = buckets
+        .entry((kind, key.to_string()))
+        .or_insert_with(|| Bucket { tokens: config.capacity, updated: Instant::now() });
+    bucket.refill(config);
+    let wait = bucket.wait_time(config);
+    if !wait.is_zero() {
+        return Err(wait)
+    }
+    if consume {
+        bucket.tokens -= 1.0;
+    }
+    Ok(())
+}
+
+/// CORS preflights are answered by the CORS middleware and count for nothing
+fn classify(req: &HttpRequest) -> Option<BucketKind> {
+    if req.method() == Method::OPTIONS {
+        return None
+    }
+    let path = req.path();
+    let is_post_upload = (path.starts_with("/f/upload") || path == "/api/v1/sessions") && req.method() == Method::POST;
+    let is_raw_upload = path.starts_with("/f/") && req.method() == Method::PUT;
+    if is_post_upload || is_raw_upload {
+        Some(BucketKind::Upload)
+    } else if ["/f/download/", "/f/preview/", "/f/thumb/", "/f/view/", "/f/browse/"].iter().any(|p| path.starts_with(p)) {
+        Some(BucketKind::Download)
+    } else if ["/f/get-info", "/f/verify/", "/f/report/", "/f/is-owner", "/f/delete/", "/f/sign/", "/f/session/", "/f/usage", "/api/", "/admin/"].iter().any(|p| path.starts_with(p)) {
+        Some(BucketKind::Metadata)
+    } else {
+        None
+    }
+}
+
+/// The session a request is about, from the `session` header or the path
+fn session_of(req: &HttpRequest) -> Option<&str> {
+    let header = req.headers().get("session").and_then(|s| s.to_str().ok());
+    let mut segments = req.path().trim_start_matches('/').split('/');
+    let from_path = match (segments.next(), segments.next(), segments.next(), segments.next()) {
+        (Some("api"), Some("v1"), Some("sessions"), session) => session,
+        (Some("f"), Some(_), session, _) => session,
+        _ => None,
+    };
+    header.or(from_path).filter(|session| is_session_id(session))
+}
+
+/// Buckets are kept per client address and, if presented, per owner token and API key.
+/// Only tokens and keys that are valid get a bucket, made up ones would fill the map and
+/// anyone could claim the ID of another key.
+fn keys(req: &HttpRequest) -> Vec<String> {
+    let mut keys = vec![format!("ip:{}", client_ip(req).unwrap_or_default())];
+    let token = req.headers().get("token").and_then(|t| t.to_str().ok());
+    if let Some((session, token)) = session_of(req).zip(token).filter(|(session, token)| is_token(session, token)) {
+        keys.push(format!("token:{session}:{token}"));
+    }
+    if let Ok(Some(key)) = apikeys::authenticate(req) {
+        keys.push(format!("key:{}", key.id));
+    }
+    keys
+}
+
+fn too_many_requests(req: ServiceRequest, wait: Duration) -> ServiceResponse<BoxBody> {
+    let seconds = wait.as_secs_f64().ceil().min(u32::MAX as f64) as u32;
+    let mut res = if req.path().starts_with("/api/") {
+        ApiError::new(StatusCode::TOO_MANY_REQUESTS, "rate_limited", "Too many requests").error_response()
+    } else {
+        HttpResponse::TooManyRequests().body("Too many requests")
+    };
+    res.headers_mut().insert(RETRY_AFTER, seconds.into());
+    req.into_response(res)
+}
+
+pub async fn rate_limit(req: ServiceRequest, next: Next<impl MessageBody + 'static>) -> Result<ServiceResponse<BoxBody>, Error> {
+    let kind = match classify(req.request()) {
+        Some(kind) if CONFIG.rate_limit.enabled => kind,
+        _ => return Ok(next.call(req).await?.map_into_boxed_body()),
+    };
+    let keys = keys(req.request());
+
+    for guard in [BucketKind::AuthFailure, kind] {
+        for key in &keys {
+            if let Err(wait) = check(guard, key, guard != BucketKind::AuthFailure) {
+                return Ok(too_many_requests(req, wait))
+            }
+        }
+    }
+
+    // Unknown sessions count as failures too, that is what guessing IDs looks like
+    let res = next.call(req).await?;
+    if kind != BucketKind::Upload && matches!(res.status(), StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN | StatusCode::NOT_FOUND) {
+        for key in &keys {
+            let _ = check(BucketKind::AuthFailure, key, true);
+        }
+    }
+    Ok(res.map_into_boxed_body())
+}
+
+/// Drops buckets that have refilled completely, they are equivalent to fresh ones
+pub async fn background_prune() {
+    loop {
+        sleep(Duration::from_secs(60)).await;
+        BUCKETS.lock().unwrap().retain(|(kind, _), bucket| {
+            let config = kind.config();
+            bucket.refill(config);
+            bucket.tokens < config.capacity
+        });
+    }
+}
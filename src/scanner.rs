@@ -0,0 +1,123 @@
+use std::fs::File;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::time::Duration;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use crate::config::CONFIG;
+
+/// Size of the chunks streamed to clamd, well below its default `StreamMaxLength`
+const CHUNK_SIZE: usize = 64 * 1024;
+
+lazy_static! {
+    static ref SCANNER: Option<Box<dyn Scanner>> = CONFIG.scan.clamd.as_ref()
+        .map(|address| Box::new(Clamd::new(address, Duration::from_secs(CONFIG.scan.timeout_secs))) as Box<dyn Scanner>);
+}
+
+/// Outcome of scanning a file, recorded in the session metadata
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum ScanStatus {
+    Clean,
+    Infected { signature: String },
+    /// The scanner could not be reached or gave no answer
+    Failed,
+}
+
+/// Checks file contents for malware
+pub trait Scanner: Send + Sync {
+    /// `None` for clean content, the name of the signature that matched otherwise
+    fn scan(&self, content: &mut dyn Read) -> io::Result<Option<String>>;
+}
+
+trait Connection: Read + Write {}
+
+impl<T: Read + Write> Connection for T {}
+
+/// ClamAV daemon, spoken to with `INSTREAM` over a unix socket (`unix:/run/clamav/clamd.ctl`) or TCP (`host:port`)
+pub struct Clamd {
+    address: String,
+    timeout: Duration,
+}
+
+impl Clamd {
+    pub fn new(address: &str, timeout: Duration) -> Self {
+        Clamd { address: address.to_string(), timeout }
+    }
+
+    fn connect(&self) -> io::Result<Box<dyn Connection>> {
+        if let Some(path) = self.address.strip_prefix("unix:") {
+            return self.connect_unix(Path::new(path))
+        }
+        let mut addresses = self.address.to_socket_addrs()?;
+        let address = addresses.next().ok_or_else(|| io::Error::new(ErrorKind::NotFound, format!("{} does not resolve", self.address)))?;
+        let stream = TcpStream::connect_timeout(&address, self.timeout)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        Ok(Box::new(stream))
+    }
+
+    #[cfg(unix)]
+    fn connect_unix(&self, path: &Path) -> io::Result<Box<dyn Connection>> {
+        let stream = std::os::unix::net::UnixStream::connect(path)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        Ok(Box::new(stream))
+    }
+
+    #[cfg(not(unix))]
+    fn connect_unix(&self, _path: &Path) -> io::Result<Box<dyn Connection>> {
+        Err(io::Error::new(ErrorKind::Unsupported, "Unix sockets are not available on this platform"))
+    }
+}
+
+impl Scanner for Clamd {
+    fn scan(&self, content: &mut dyn Read) -> io::Result<Option<String>> {
+        let mut connection = self.connect()?;
+        connection.write_all(b"zINSTREAM\0")?;
+        // Length prefixed chunks, a zero length one ends the stream
+        let mut buffer = vec![0; CHUNK_SIZE];
+        loop {
+            let read = match content.read(&mut buffer) {
+                Ok(read) => read,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            connection.write_all(&(read as u32).to_be_bytes())?;
+            if read == 0 {
+                break
+            }
+            connection.write_all(&buffer[..read])?;
+        }
+        let mut reply = vec![];
+        connection.read_to_end(&mut reply)?;
+        parse_reply(&String::from_utf8_lossy(&reply))
+    }
+}
+
+/// `stream: OK`, `stream: <signature> FOUND` or `<message> ERROR`
+fn parse_reply(reply: &str) -> io::Result<Option<String>> {
+    let reply = reply.trim_end_matches(['\0', '\n']);
+    let result = reply.strip_prefix("stream: ").unwrap_or(reply);
+    if result == "OK" {
+        Ok(None)
+    } else if let Some(signature) = result.strip_suffix(" FOUND") {
+        Ok(Some(signature.to_string()))
+    } else {
+        Err(io::Error::other(format!("clamd replied: {reply}")))
+    }
+}
+
+/// Scans a written file with the configured scanner, `None` if there is none
+pub fn scan_file(path: &Path) -> Option<ScanStatus> {
+    let scanner = SCANNER.as_ref()?;
+    Some(match File::open(path).and_then(|mut file| scanner.scan(&mut file)) {
+        Ok(None) => ScanStatus::Clean,
+        Ok(Some(signature)) => ScanStatus::Infected { signature },
+        Err(err) => {
+            eprintln!("Error scanning {}: {}", path.display(), err);
+            ScanStatus::Failed
+        }
+    })
+}
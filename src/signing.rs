@@ -0,0 +1,159 @@
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+use actix_web::{web, HttpRequest};
+use hmac::{Hmac, Mac};
+use lazy_static::lazy_static;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use sha2::Sha256;
+use crate::{client_ip, get_hostname, random_str};
+
+const SECRET_FILE: &str = ".secret";
+const SECRET_LEN: usize = 64;
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'_').remove(b'.').remove(b'~');
+
+lazy_static! {
+    static ref SECRET: Vec<u8> = {
+        if fs::exists(SECRET_FILE).expect("FAILED TO FIND OUT IF .secret EXISTS") {
+            fs::read(SECRET_FILE).expect("FAILED TO READ .secret")
+        } else {
+            let secret = random_str(SECRET_LEN);
+            fs::write(SECRET_FILE, &secret).expect("FAILED TO WRITE .secret");
+            secret.into_bytes()
+        }
+    };
+}
+
+/// What a signature grants access to.
+/// `file` being `None` means every file in the session (and the archive),
+/// `ip` being `Some` binds the link to a single client address.
+pub struct LinkScope<'a> {
+    pub session: &'a str,
+    pub file: Option<&'a str>,
+    pub expires: u64,
+    pub ip: Option<&'a str>,
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+fn mac() -> Hmac<Sha256> {
+    Hmac::<Sha256>::new_from_slice(&SECRET).expect("HMAC accepts keys of any length")
+}
+
+fn message(scope: &LinkScope) -> String {
+    format!("{}\n{}\n{}\n{}", scope.session, scope.file.unwrap_or(""), scope.expires, scope.ip.unwrap_or(""))
+}
+
+pub fn sign(scope: &LinkScope) -> String {
+    let mut mac = mac();
+    mac.update(message(scope).as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+pub fn verify(scope: &LinkScope, signature: &str) -> bool {
+    if scope.expires < now() {
+        return false
+    }
+    let Ok(signature) = hex::decode(signature) else {
+        return false
+    };
+    let mut mac = mac();
+    mac.update(message(scope).as_bytes());
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// Token the owner page has to echo back in the `csrf-token` header when acting with the session cookie
+pub fn csrf_token(session: &str, owner_token: &str) -> String {
+    let mut mac = mac();
+    mac.update(format!("csrf\n{session}\n{owner_token}").as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+pub fn verify_csrf(session: &str, owner_token: &str, csrf: &str) -> bool {
+    let Ok(csrf) = hex::decode(csrf) else {
+        return false
+    };
+    let mut mac = mac();
+    mac.update(format!("csrf\n{session}\n{owner_token}").as_bytes());
+    mac.verify_slice(&csrf).is_ok()
+}
+
+/// Token the admin pages put into their forms, the browser sends the basic auth credentials along with any site's request
+pub fn admin_csrf_token(user: &str) -> String {
+    let mut mac = mac();
+    mac.update(format!("admin-csrf\n{user}").as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+pub fn verify_admin_csrf(user: &str, csrf: &str) -> bool {
+    let Ok(csrf) = hex::decode(csrf) else {
+        return false
+    };
+    let mut mac = mac();
+    mac.update(format!("admin-csrf\n{user}").as_bytes());
+    mac.verify_slice(&csrf).is_ok()
+}
+
+pub fn encode_segment(segment: &str) -> String {
+    utf8_percent_encode(segment, PATH_SEGMENT).to_string()
+}
+
+/// Encodes every segment of a `/` separated path
+pub fn encode_path(path: &str) -> String {
+    path.split('/').map(encode_segment).collect::<Vec<_>>().join("/")
+}
+
+/// Query string (without the leading `?`) carrying a signature for `scope`.
+pub fn signed_query(scope: &LinkScope) -> String {
+    let mut query = format!("expires={}&sig={}", scope.expires, sign(scope));
+    if scope.file.is_none() {
+        query.push_str("&all=1");
+    }
+    if scope.ip.is_some() {
+        query.push_str("&ip=1");
+    }
+    query
+}
+
+pub fn signed_url(scope: &LinkScope) -> String {
+    let path = match scope.file {
+        Some(file) => format!("/f/download/{}/{}", scope.session, encode_path(file)),
+        None => format!("/f/download/{}", scope.session),
+    };
+    format!("https://{}{}?{}", get_hostname(), path, signed_query(scope))
+}
+
+pub fn query_param(req: &HttpRequest, key: &str) -> Option<String> {
+    web::Query::<Vec<(String, String)>>::from_query(req.query_string())
+        .ok()?
+        .into_inner()
+        .into_iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v)
+}
+
+/// Checks the `expires`/`sig` query of a download request.
+/// `file` is the requested file, or `None` when the whole session is requested.
+pub fn verify_request(req: &HttpRequest, session: &str, file: Option<&str>) -> bool {
+    let (Some(expires), Some(signature)) = (query_param(req, "expires"), query_param(req, "sig")) else {
+        return false
+    };
+    let Ok(expires) = expires.parse::<u64>() else {
+        return false
+    };
+    let file = match query_param(req, "all") {
+        Some(_) => None,
+        None if file.is_some() => file,
+        None => return false,
+    };
+    let ip = match query_param(req, "ip") {
+        Some(_) => match client_ip(req) {
+            Some(ip) => Some(ip),
+            None => return false,
+        },
+        None => None,
+    };
+
+    verify(&LinkScope { session, file, expires, ip: ip.as_deref() }, &signature)
+}
@@ -0,0 +1,145 @@
+use std::io::{self, Read};
+use std::path::Path;
+use lazy_static::lazy_static;
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+use syntect::highlighting::ThemeSet;
+use syntect::html::{css_for_theme_with_class_style, ClassStyle, ClassedHTMLGenerator};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+use crate::compression;
+
+/// Larger files are cut off, the rest is only available as download
+const MAX_VIEW_BYTES: u64 = 512 * 1024;
+const MAX_VIEW_LINES: usize = 10_000;
+const MARKDOWN_EXTENSIONS: [&str; 3] = ["md", "markdown", "mdown"];
+/// Schemes links in rendered Markdown may use, relative links are always fine
+const LINK_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+const THEME: &str = "base16-ocean.dark";
+
+lazy_static! {
+    static ref SYNTAXES: SyntaxSet = SyntaxSet::load_defaults_newlines();
+    /// Served as `/r/highlight.css`, the page policy does not allow inline styles
+    pub static ref HIGHLIGHT_CSS: String = ThemeSet::load_defaults().themes.get(THEME)
+        .and_then(|theme| css_for_theme_with_class_style(theme, CLASS_STYLE).ok())
+        .unwrap_or_default();
+}
+
+pub struct TextView {
+    /// Detected language, `Markdown` for rendered documents
+    pub language: String,
+    /// Lines shown, the rendered document counts as one
+    pub lines: usize,
+    pub truncated: bool,
+    pub html: String,
+}
+
+/// Reads at most `MAX_VIEW_BYTES`, cut back to the last complete line if the file is longer
+fn read_text(path: &Path, compressed: bool) -> io::Result<(String, bool)> {
+    let mut bytes = vec![];
+    compression::open(path, compressed)?.take(MAX_VIEW_BYTES + 1).read_to_end(&mut bytes)?;
+    let truncated = bytes.len() as u64 > MAX_VIEW_BYTES;
+    if truncated {
+        let end = bytes.iter().rposition(|b| *b == b'\n').map_or(MAX_VIEW_BYTES as usize, |i| i + 1);
+        bytes.truncate(end);
+    }
+    Ok((String::from_utf8_lossy(&bytes).into_owned(), truncated))
+}
+
+fn is_markdown(filename: &str) -> bool {
+    Path::new(filename).extension()
+        .is_some_and(|e| MARKDOWN_EXTENSIONS.contains(&e.to_string_lossy().to_ascii_lowercase().as_str()))
+}
+
+/// By extension, then by the whole file name (`Makefile`), then by the first line (shebangs)
+fn detect_syntax(filename: &str, text: &str) -> &'static SyntaxReference {
+    let path = Path::new(filename);
+    path.extension()
+        .and_then(|e| SYNTAXES.find_syntax_by_extension(&e.to_string_lossy()))
+        .or_else(|| path.file_name().and_then(|name| SYNTAXES.find_syntax_by_extension(&name.to_string_lossy())))
+        .or_else(|| SYNTAXES.find_syntax_by_first_line(text))
+        .unwrap_or_else(|| SYNTAXES.find_syntax_plain_text())
+}
+
+/// Renders the file for `/f/view`, as highlighted source with numbered lines or as Markdown document
+pub fn render(path: &Path, filename: &str, compressed: bool) -> io::Result<TextView> {
+    let (mut text, mut truncated) = read_text(path, compressed)?;
+    if let Some((end, _)) = text.match_indices('\n').nth(MAX_VIEW_LINES - 1) {
+        text.truncate(end + 1);
+        truncated = true;
+    }
+    if is_markdown(filename) {
+        return Ok(TextView { language: "Markdown".to_string(), lines: 1, truncated, html: render_markdown(&text) })
+    }
+
+    let syntax = detect_syntax(filename, &text);
+    let lines = highlight(&text, syntax).unwrap_or_else(|| LinesWithEndings::from(&text).map(escape_line).collect());
+    let html = lines.iter().enumerate()
+        .map(|(i, line)| format!(
+            "<div class=\"code-line\" id=\"L{n}\"><a class=\"line-number\" href=\"#L{n}\">{n}</a><span class=\"code-text\">{line}</span></div>",
+            n = i + 1
+        ))
+        .collect();
+    Ok(TextView { language: syntax.name.clone(), lines: lines.len(), truncated, html })
+}
+
+fn escape_line(line: &str) -> String {
+    line.trim_end_matches(['\r', '\n']).replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Highlighted HTML per line, `None` if the syntax definition fails on the input
+fn highlight(text: &str, syntax: &SyntaxReference) -> Option<Vec<String>> {
+    let mut generator = ClassedHTMLGenerator::new_with_class_style(syntax, &SYNTAXES, CLASS_STYLE);
+    for line in LinesWithEndings::from(text) {
+        generator.parse_html_for_line_which_includes_newline(line).ok()?;
+    }
+    let html = generator.finalize();
+
+    // Spans run across line ends, every line gets closed and the open ones reopened on the next
+    let mut open: Vec<&str> = vec![];
+    let mut lines = vec![];
+    for line in html.split('\n').take(text.lines().count()) {
+        let mut rendered = open.concat();
+        let mut rest = line;
+        while let Some(start) = rest.find('<') {
+            let end = rest[start..].find('>').map_or(rest.len(), |end| start + end + 1);
+            match &rest[start..end] {
+                "</span>" => { open.pop(); }
+                tag => open.push(tag),
+            }
+            rest = &rest[end..];
+        }
+        rendered.push_str(line.trim_end_matches('\r'));
+        rendered.push_str(&"</span>".repeat(open.len()));
+        lines.push(rendered);
+    }
+    Some(lines)
+}
+
+/// Markdown with raw HTML shown as text and links restricted to `LINK_SCHEMES`
+fn render_markdown(text: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS | Options::ENABLE_FOOTNOTES;
+    let events = Parser::new_ext(text, options).map(|event| match event {
+        Event::Html(raw) | Event::InlineHtml(raw) => Event::Text(raw),
+        Event::Start(Tag::Link { link_type, dest_url, title, id }) => {
+            Event::Start(Tag::Link { link_type, dest_url: safe_url(dest_url), title, id })
+        }
+        Event::Start(Tag::Image { link_type, dest_url, title, id }) => {
+            Event::Start(Tag::Image { link_type, dest_url: safe_url(dest_url), title, id })
+        }
+        event => event,
+    });
+    let mut html = String::new();
+    html::push_html(&mut html, events);
+    html
+}
+
+fn safe_url(url: CowStr) -> CowStr {
+    // Browsers ignore whitespace and control characters within the scheme
+    let cleaned: String = url.chars().filter(|c| !c.is_ascii_whitespace() && !c.is_control()).collect();
+    let scheme = cleaned.split_once(':').map(|(scheme, _)| scheme).filter(|scheme| !scheme.contains(['/', '?', '#']));
+    match scheme {
+        Some(scheme) if !LINK_SCHEMES.contains(&scheme.to_ascii_lowercase().as_str()) => CowStr::Borrowed("#"),
+        _ => url,
+    }
+}
@@ -0,0 +1,74 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use actix_files::NamedFile;
+use actix_web::{web, Error, HttpRequest, HttpResponse};
+use image::{ImageFormat, ImageReader, ImageResult, Limits};
+use sha2::{Digest, Sha256};
+use crate::fileupload::{authorize_download, session_file};
+use crate::random_str;
+
+/// Cache directory inside the session, removed together with it
+pub const THUMB_DIR: &str = ".thumbs";
+/// Longest edge of a thumbnail
+const THUMB_SIZE: u32 = 256;
+/// Larger images are not decoded at all
+const MAX_DIMENSION: u32 = 12_000;
+/// Memory a single decode may allocate
+const MAX_DECODE_ALLOC: u64 = 256 * 1024 * 1024;
+const FORMATS: [ImageFormat; 5] = [ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::Gif, ImageFormat::WebP, ImageFormat::Bmp];
+
+/// Whether the file name suggests a format thumbnails are made of
+pub fn has_thumbnail(filename: &str) -> bool {
+    ImageFormat::from_path(filename).is_ok_and(|format| FORMATS.contains(&format))
+}
+
+fn thumb_path(session_id: &str, filename: &str) -> PathBuf {
+    let name = hex::encode(Sha256::digest(filename.as_bytes()));
+    PathBuf::from(format!("sessions/{session_id}/{THUMB_DIR}/{name}.jpg"))
+}
+
+/// Decodes `source` within the limits and writes a JPEG thumbnail to `target`.
+/// The format is sniffed from the content, the extension is not trusted.
+fn generate(source: &Path, target: &Path) -> ImageResult<()> {
+    let mut reader = ImageReader::open(source)?.with_guessed_format()?;
+    if !reader.format().is_some_and(|format| FORMATS.contains(&format)) {
+        return Err(image::ImageError::Unsupported(image::error::ImageFormatHint::Unknown.into()))
+    }
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+    reader.limits(limits);
+
+    let thumb = reader.decode()?.thumbnail(THUMB_SIZE, THUMB_SIZE).into_rgb8();
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Written aside first, so concurrent requests never serve a partial file
+    let partial = target.with_extension(format!("{}.part", random_str(8)));
+    thumb.save_with_format(&partial, ImageFormat::Jpeg)?;
+    fs::rename(&partial, target)?;
+    Ok(())
+}
+
+/// Serves a thumbnail of an uploaded image, generating it on first request
+pub async fn thumbnail(req: HttpRequest, path: web::Path<(String, String)>) -> Result<HttpResponse, Error> {
+    let (session_id, filename) = path.into_inner();
+    let Some(source) = session_file(&session_id, &filename).filter(|_| has_thumbnail(&filename)) else {
+        return Ok(HttpResponse::NotFound().body("Non existent image within session"))
+    };
+    if let Some(res) = authorize_download(&req, &session_id, Some(&filename)) {
+        return Ok(res)
+    }
+    let target = thumb_path(&session_id, &filename);
+    if !target.is_file() {
+        let cached = target.clone();
+        if web::block(move || generate(Path::new(&source), &cached)).await?.is_err() {
+            return Ok(HttpResponse::UnsupportedMediaType().body("Image could not be decoded for a thumbnail"))
+        }
+    }
+    let file = NamedFile::open(&target)?
+        .set_content_type(mime::IMAGE_JPEG)
+        .use_last_modified(true);
+    Ok(file.into_response(&req))
+}
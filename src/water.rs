@@ -1,15 +1,43 @@
+use std::collections::BTreeMap;
 use std::fs;
 use std::fs::FileType;
 use std::path::{Path, PathBuf};
 use chrono::{DateTime, Local, TimeZone, Utc};
 use futures_util::io;
 use crate::{get_hostname, random_str};
+use crate::abuse::Case;
+use crate::archive::ArchiveKind;
+use crate::fileupload::{is_textual, preview_type};
+use crate::meta::{FileMeta, SessionMeta};
+use crate::scanner::ScanStatus;
+use crate::textview::TextView;
+use crate::signing::encode_path;
+use crate::thumbnail::has_thumbnail;
 
 const STYLE_FILE: &str = include_str!("../style.css");
 const FILE_UPLOAD_INDEX: &str = include_str!("../fup-index.html");
 const INDEX: &str = include_str!("../index.html");
 const FUP_SESSION: &str = include_str!("../fup-session.html");
 const ERROR_TEMPLATE: &str = include_str!("../error.html");
+const API_EXPLORER: &str = include_str!("../api-explorer.html");
+const FUP_ARCHIVE: &str = include_str!("../fup-archive.html");
+const FUP_VIEW: &str = include_str!("../fup-view.html");
+const FUP_ADMIN: &str = include_str!("../fup-admin.html");
+const SCRIPTS: [(&str, &str); 5] = [
+    ("index.js", include_str!("../index.js")),
+    ("fup-index.js", include_str!("../fup-index.js")),
+    ("fup-session.js", include_str!("../fup-session.js")),
+    ("fup-view.js", include_str!("../fup-view.js")),
+    ("api-explorer.js", include_str!("../api-explorer.js")),
+];
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
 
 fn format_file_size(size: u64) -> String {
     let units = ["B", "KB", "MB", "GB", "TB", "PB"];
@@ -25,45 +53,243 @@ fn format_file_size(size: u64) -> String {
 }
 
 fn format_utc_time(timestamp: u64) -> String {
-    let datetime: DateTime<Utc> = Utc.timestamp_opt(timestamp as i64, 0).unwrap();
-    datetime.format("%Y-%m-%d %H:%M:%S UTC").to_string()
+    // Expiration files written before lifetimes were limited may hold anything
+    let datetime: Option<DateTime<Utc>> = i64::try_from(timestamp).ok().and_then(|t| Utc.timestamp_opt(t, 0).single());
+    match datetime {
+        Some(datetime) => datetime.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        None => "never".to_string(),
+    }
 }
 
-pub fn load_all(sid: String, expiration: u64, files: Vec<(String, u64)>, token: Option<String>) -> String {
-    let file_items = files
-        .into_iter()
-        .map(|(filename, filesize)| {
-            let str = random_str(12);
-            format!(
-                "<div class=\"file-item\">
-                    <span id=\"{id}\" class=\"file-info\">{filename}</span>
-                    <h3 class=\"file-size\">{size}</h3>
-                    <button class=\"download-btn\" onclick=\"downloadFile('{filename}', '{id}')\">Download</button>
-                 </div>",
-                id = str,
-                filename = filename,
-                size = format_file_size(filesize)
-            )
-        })
+fn file_item(name: &str, path: &str, size: u64, meta: Option<&FileMeta>) -> String {
+    let id = random_str(12);
+    let scan = meta.and_then(|meta| meta.scan.as_ref());
+    if meta.is_some_and(|meta| meta.quarantined) {
+        let reason = match scan {
+            Some(ScanStatus::Infected { signature }) => format!(" title=\"Malware scanner: {}\"", escape_html(signature)),
+            _ => String::new(),
+        };
+        return format!(
+            "<div class=\"file-item\">
+                <span id=\"{id}\" class=\"file-info\">{name}</span>
+                <h3 class=\"file-size\">{size}</h3>
+                <span class=\"quarantined\"{reason}>Quarantined</span>
+             </div>",
+            name = escape_html(name),
+            size = format_file_size(size)
+        )
+    }
+    let browse = match ArchiveKind::of(path) {
+        Some(_) => format!("<button class=\"browse-btn\" data-filename=\"{}\">Browse</button>", escape_html(path)),
+        None => String::new(),
+    };
+    let textual = meta.map_or_else(|| is_textual(path), FileMeta::is_text);
+    let preview = match preview_type(path, meta) {
+        // Text gets the full page view instead
+        _ if textual => format!("<button class=\"view-btn\" data-filename=\"{}\">View</button>", escape_html(path)),
+        Some((_, kind)) => format!(
+            "<button class=\"preview-btn\" data-filename=\"{}\" data-preview=\"{}\">Preview</button>",
+            escape_html(path),
+            kind.name()
+        ),
+        None => String::new(),
+    };
+    let hash = match meta.and_then(|meta| meta.sha256.as_deref()) {
+        Some(sha256) => format!(
+            "<span class=\"file-hash\" title=\"SHA-256, click to copy\" data-hash=\"{sha256}\">{}…</span>",
+            &sha256[..sha256.len().min(12)]
+        ),
+        None => String::new(),
+    };
+    let scan = match scan {
+        Some(ScanStatus::Clean) => "<span class=\"scan-clean\" title=\"No malware found\">Scanned</span>".to_string(),
+        Some(ScanStatus::Infected { signature }) => format!(
+            "<span class=\"scan-flagged\" title=\"Reported by the malware scanner\">Malware: {}</span>",
+            escape_html(signature)
+        ),
+        Some(ScanStatus::Failed) => "<span class=\"scan-failed\" title=\"The malware scanner was unavailable\">Not scanned</span>".to_string(),
+        None => String::new(),
+    };
+    let thumb = match has_thumbnail(path) {
+        true => format!("<img class=\"thumb\" data-filename=\"{}\" alt=\"\">", escape_html(path)),
+        false => String::new(),
+    };
+    format!(
+        "<div class=\"file-item\">
+            {thumb}
+            <span id=\"{id}\" class=\"file-info\">{name}</span>
+            <h3 class=\"file-size\">{size}</h3>
+            {hash}
+            {scan}
+            {preview}
+            {browse}
+            <button class=\"download-btn\" data-filename=\"{path}\" data-target=\"{id}\">Download</button>
+         </div>",
+        name = escape_html(name),
+        path = escape_html(path),
+        size = format_file_size(size)
+    )
+}
+
+/// Renders `(path below this folder, full path, size)` entries, subfolders first as collapsible groups
+fn folder_items(entries: Vec<(&str, &str, u64)>, meta: &SessionMeta) -> String {
+    let mut folders: BTreeMap<&str, Vec<(&str, &str, u64)>> = BTreeMap::new();
+    let mut files = vec![];
+    for (rest, path, size) in entries {
+        match rest.split_once('/') {
+            Some((folder, rest)) => folders.entry(folder).or_default().push((rest, path, size)),
+            None => files.push(file_item(rest, path, size, meta.get(path))),
+        }
+    }
+    folders.into_iter()
+        .map(|(name, entries)| format!(
+            "<details class=\"folder\" open><summary class=\"folder-name\">{}/</summary>{}</details>",
+            escape_html(name),
+            folder_items(entries, meta)
+        ))
+        .chain(files)
         .collect::<Vec<_>>()
-        .join("\n");
+        .join("\n")
+}
 
-    let html_template = include_str!("../fup-session.html");
+pub fn load_all(sid: String, expiration: u64, files: Vec<(String, u64)>, meta: &SessionMeta, csrf: Option<String>, link_query: Option<String>) -> String {
+    let file_items = folder_items(files.iter().map(|(path, size)| (path.as_str(), path.as_str(), *size)).collect(), meta);
 
-    html_template
+    FUP_SESSION
         .replace("{{sid}}", &sid)
         .replace("{{expires}}", &format_utc_time(expiration))
         .replace("{{file_items}}", &file_items)
         .replace("{{hostname}}", &get_hostname())
+        .replace("{{link_query}}", &escape_html(&link_query.map(|q| format!("?{q}")).unwrap_or_default()))
         .replace(
             "{{delete_button}}",
-            &if token.is_some() {
-                "<button class=\"delete-all-btn\" onclick=\"deleteAll()\">Delete All</button>".to_string()
+            &if csrf.is_some() {
+                "<button class=\"delete-all-btn\" id=\"delete-all\">Delete All</button>".to_string()
             } else {
                 "".to_string()
             },
         )
-        .replace("{{token}}", &token.unwrap_or("YOU ARE NOT THE OWNER".to_string()))
+        .replace("{{csrf}}", &csrf.unwrap_or_default())
+}
+
+/// The browse view of an archive, `entries` as `(name, size, is_dir)`
+pub fn load_archive(sid: &str, file: &str, entries: Vec<(String, u64, bool)>) -> String {
+    let total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+    let summary = format!("{} entries, {} uncompressed", entries.len(), format_file_size(total));
+    let items = entries
+        .into_iter()
+        .map(|(name, size, is_dir)| format!(
+            "<div class=\"file-item\">
+                <span class=\"file-info\">{name}</span>
+                <h3 class=\"file-size\">{size}</h3>
+             </div>",
+            name = escape_html(&name),
+            size = if is_dir { "folder".to_string() } else { format_file_size(size) }
+        ))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    FUP_ARCHIVE
+        .replace("{{sid}}", sid)
+        .replace("{{file}}", &escape_html(file))
+        .replace("{{summary}}", &summary)
+        .replace("{{entries}}", &items)
+}
+
+/// The text view of a file, `link_query` being the signature it was opened with
+pub fn load_view(sid: &str, file: &str, view: TextView, link_query: Option<String>) -> String {
+    let link_query = escape_html(&link_query.map(|q| format!("?{q}")).unwrap_or_default());
+    let download = format!("/f/download/{sid}/{}{link_query}", escape_html(&encode_path(file)));
+    let summary = match view.language.as_str() {
+        "Markdown" => "Markdown".to_string(),
+        language => format!("{language}, {} lines", view.lines),
+    };
+    let notice = match view.truncated {
+        true => format!("<p class=\"view-notice\">The file is too large to show completely. <a href=\"{download}\">Download the full file</a></p>"),
+        false => String::new(),
+    };
+    let class = if view.language == "Markdown" { "markdown" } else { "code" };
+
+    // The file content goes in last, so placeholders in it stay untouched
+    FUP_VIEW
+        .replace("{{sid}}", sid)
+        .replace("{{file}}", &escape_html(file))
+        .replace("{{summary}}", &escape_html(&summary))
+        .replace("{{download}}", &download)
+        .replace("{{notice}}", &notice)
+        .replace("{{class}}", class)
+        .replace("{{content}}", &view.html)
+}
+
+/// The review page of the abuse queue, every action a form carrying `csrf`
+pub fn load_admin(cases: &[Case], csrf: &str) -> String {
+    let action = |session: &str, action: &str, label: &str, class: &str| format!(
+        "<form method=\"post\" action=\"/admin/reports/{session}/{action}\">
+            <input type=\"hidden\" name=\"csrf\" value=\"{csrf}\">
+            <button class=\"{class}\">{label}</button>
+         </form>"
+    );
+    let items = cases.iter()
+        .map(|case| {
+            let session = escape_html(&case.session);
+            let state = match (&case.files, case.disabled) {
+                (None, _) => "Gone",
+                (Some(_), true) => "Disabled",
+                (Some(_), false) => "Active",
+            };
+            let reports = case.reports.iter()
+                .map(|report| format!(
+                    "<li><span class=\"report-time\">{time}</span> {reason}{contact}</li>",
+                    time = format_utc_time(report.created),
+                    reason = escape_html(&report.reason),
+                    contact = report.contact.as_ref()
+                        .map(|contact| format!(" <span class=\"report-contact\">{}</span>", escape_html(contact)))
+                        .unwrap_or_default()
+                ))
+                .collect::<String>();
+            let files = case.files.iter().flatten()
+                .map(|(name, size)| format!(
+                    "<li>{} <span class=\"report-time\">{}</span></li>",
+                    escape_html(name),
+                    format_file_size(*size)
+                ))
+                .collect::<String>();
+            let restore = if case.disabled { "Restore" } else { "Dismiss" };
+            let actions = match case.files {
+                Some(_) => [
+                    action(&session, "restore", restore, "restore-btn"),
+                    action(&session, "delete", "Delete", "delete-btn"),
+                    action(&session, "blocklist", "Delete and blocklist", "blocklist-btn"),
+                ].concat(),
+                None => action(&session, "restore", "Dismiss", "restore-btn"),
+            };
+            format!(
+                "<div class=\"report-case\">
+                    <div class=\"report-header\">
+                        <span class=\"file-info\">{session}</span>
+                        <span class=\"report-state\">{state}, {count}</span>
+                    </div>
+                    <ul class=\"report-entries\">{reports}</ul>
+                    <ul class=\"report-files\">{files}</ul>
+                    <div class=\"report-actions\">{actions}</div>
+                 </div>",
+                count = match case.reports.len() {
+                    1 => "1 open report".to_string(),
+                    n => format!("{n} open reports"),
+                }
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let summary = match cases.len() {
+        0 => "No open reports".to_string(),
+        1 => "1 session with open reports".to_string(),
+        n => format!("{n} sessions with open reports"),
+    };
+
+    FUP_ADMIN
+        .replace("{{summary}}", &summary)
+        .replace("{{cases}}", &items)
 }
 
 pub fn get_style() -> io::Result<String> {
@@ -74,6 +300,26 @@ pub fn get_style() -> io::Result<String> {
     }
 }
 
+/// One of the bundled scripts, a file of the same name in the working directory takes precedence
+pub fn get_script(name: &str) -> io::Result<Option<String>> {
+    let Some((name, bundled)) = SCRIPTS.iter().find(|(script, _)| *script == name) else {
+        return Ok(None)
+    };
+    if fs::exists(name)? {
+        fs::read_to_string(name).map(Some)
+    } else {
+        Ok(Some(bundled.to_string()))
+    }
+}
+
+pub fn get_api_explorer() -> io::Result<String> {
+    if fs::exists("api-explorer.html")? {
+        fs::read_to_string("api-explorer.html")
+    } else {
+        Ok(API_EXPLORER.to_string())
+    }
+}
+
 pub fn get_fileupload_index() -> io::Result<String> {
     if fs::exists("fup-index.html")? {
         fs::read_to_string("fup-index.html")
@@ -93,10 +339,10 @@ pub fn get_index() -> io::Result<String> {
     let articles = get_articles2()?;
     for article in articles {
         let title = article.0;
-        boxes.push(format!("<div class=\"article-box\" onclick=\"window.location = '/a/{title}'\">
+        boxes.push(format!("<a class=\"article-box\" href=\"/a/{title}\">
       <div class=\"article-title\">{title}</div>
       <div class=\"article-meta\">by MOBSkuchen — {}</div>
-    </div>", article.1))
+    </a>", article.1))
     }
 
     Ok(content.replace("{{articles}}", &boxes.join("\n")))
@@ -0,0 +1,151 @@
+mod common;
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+use fup_client::{Client, CreateOptions, SessionCreated, Upload};
+use reqwest::redirect::Policy;
+use serde_json::json;
+use common::server;
+
+async fn upload(name: &str, content: &str) -> SessionCreated {
+    Client::new(server()).unwrap()
+        .create_session(vec![Upload::bytes(name.to_string(), content.to_string())], &CreateOptions::default()).await.unwrap()
+}
+
+/// Reports the session as coming from `ip`, the test server trusts the local proxy
+async fn report(session: &str, ip: &str, reason: &str) -> reqwest::Response {
+    reqwest::Client::new().post(format!("{}/f/report/{session}", server()))
+        .header("x-forwarded-for", ip)
+        .json(&json!({ "reason": reason, "contact": "abuse@example.org" }))
+        .send().await.unwrap()
+}
+
+fn admin_client() -> reqwest::Client {
+    reqwest::Client::builder().redirect(Policy::none()).build().unwrap()
+}
+
+async fn review_page() -> String {
+    admin_client().get(format!("{}/admin/reports", server()))
+        .basic_auth("admin", Some("secret"))
+        .send().await.unwrap().text().await.unwrap()
+}
+
+fn csrf(page: &str) -> &str {
+    let start = page.find("name=\"csrf\" value=\"").unwrap() + "name=\"csrf\" value=\"".len();
+    &page[start..start + page[start..].find('"').unwrap()]
+}
+
+async fn resolve(session: &str, action: &str, csrf: &str) -> reqwest::Response {
+    admin_client().post(format!("{}/admin/reports/{session}/{action}", server()))
+        .basic_auth("admin", Some("secret"))
+        .form(&[("csrf", csrf)])
+        .send().await.unwrap()
+}
+
+async fn download_status(session: &str, file: &str) -> u16 {
+    reqwest::get(format!("{}/f/download/{session}/{file}", server())).await.unwrap().status().as_u16()
+}
+
+#[actix_web::test]
+async fn reports_disable_until_restored() {
+    let created = upload("shared.txt", "reported but harmless").await;
+    let session = &created.session;
+
+    assert_eq!(report(session, "203.0.113.1", "Phishing page").await.status(), 202);
+    // The same client counts once
+    assert_eq!(report(session, "203.0.113.1", "Still phishing").await.status(), 202);
+    assert_eq!(download_status(session, "shared.txt").await, 200);
+
+    assert_eq!(report(session, "203.0.113.2", "Looks like <b>malware</b>").await.status(), 202);
+    assert_eq!(download_status(session, "shared.txt").await, 403);
+    let page = reqwest::get(format!("{}/f/session/{session}", server())).await.unwrap();
+    assert_eq!(page.status(), 403);
+    let verify = reqwest::Client::new().get(format!("{}/f/verify/{session}/shared.txt", server()))
+        .header("sha256", created.files[0].sha256.as_deref().unwrap())
+        .send().await.unwrap();
+    assert_eq!(verify.status(), 403);
+    let client = Client::new(server()).unwrap();
+    assert_eq!(client.extend(session, Some(&created.token), 60).await.unwrap_err().code(), Some("session_disabled"));
+    assert_eq!(client.delete(session, Some(&created.token)).await.unwrap_err().code(), Some("session_disabled"));
+    let res = reqwest::Client::new().post(format!("{}/f/delete/{session}", server()))
+        .header("token", &created.token)
+        .send().await.unwrap();
+    assert_eq!(res.status(), 403);
+
+    let page = review_page().await;
+    assert!(page.contains(session.as_str()), "{page}");
+    assert!(page.contains("Disabled, 2 open reports"), "{page}");
+    assert!(page.contains("Phishing page"));
+    assert!(!page.contains("Still phishing"));
+    assert!(page.contains("Looks like &lt;b&gt;malware&lt;/b&gt;"));
+    assert!(page.contains("abuse@example.org"));
+
+    assert_eq!(resolve(session, "restore", "00").await.status(), 403);
+    let res = resolve(session, "restore", csrf(&page)).await;
+    assert_eq!(res.status(), 303);
+    assert_eq!(res.headers()["location"], "/admin/reports");
+    assert_eq!(download_status(session, "shared.txt").await, 200);
+    assert!(!review_page().await.contains(session.as_str()));
+    client.extend(session, Some(&created.token), 60).await.unwrap();
+}
+
+#[actix_web::test]
+async fn blocklisting_deletes_and_refuses_reuploads() {
+    let content = "material that was taken down";
+    let created = upload("takedown.txt", content).await;
+    report(&created.session, "198.51.100.7", "Copyright infringement").await;
+
+    let page = review_page().await;
+    assert!(page.contains("Active, 1 open report"), "{page}");
+    assert_eq!(resolve(&created.session, "blocklist", csrf(&page)).await.status(), 303);
+    assert_eq!(download_status(&created.session, "takedown.txt").await, 404);
+
+    let err = Client::new(server()).unwrap()
+        .create_session(vec![Upload::bytes("again.txt", content)], &CreateOptions::default()).await.unwrap_err();
+    assert_eq!(err.code(), Some("blocked_content"));
+}
+
+#[actix_web::test]
+async fn admin_pages_are_not_compressed() {
+    let res = admin_client().get(format!("{}/admin/reports", server()))
+        .basic_auth("admin", Some("secret"))
+        .header("accept-encoding", "gzip, br, zstd")
+        .send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    assert!(!res.headers().contains_key("content-encoding"));
+}
+
+#[actix_web::test]
+async fn reports_and_admin_access_are_checked() {
+    let created = upload("fine.txt", "fine").await;
+    assert_eq!(report(&created.session, "192.0.2.1", "  ").await.status(), 400);
+    assert_eq!(report("doesnotexist", "192.0.2.1", "Spam").await.status(), 404);
+
+    let res = admin_client().get(format!("{}/admin/reports", server())).send().await.unwrap();
+    assert_eq!(res.status(), 401);
+    assert!(res.headers()["www-authenticate"].to_str().unwrap().starts_with("Basic"));
+    let res = admin_client().get(format!("{}/admin/reports", server()))
+        .basic_auth("admin", Some("wrong"))
+        .send().await.unwrap();
+    assert_eq!(res.status(), 401);
+}
+
+#[test]
+fn admin_passwords_are_hashed_with_a_salt() {
+    server();
+    let hash = |password: &str| {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_fileupload"))
+            .arg("admin-password")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn().unwrap();
+        child.stdin.take().unwrap().write_all(password.as_bytes()).unwrap();
+        let output = child.wait_with_output().unwrap();
+        (output.status.code(), String::from_utf8(output.stdout).unwrap())
+    };
+    let (status, first) = hash("secret\n");
+    assert_eq!(status, Some(0));
+    assert!(first.starts_with("pbkdf2-sha256$600000$"), "{first}");
+    assert_ne!(first, hash("secret\n").1);
+    assert_eq!(hash("\n").0, Some(2));
+}
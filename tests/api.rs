@@ -0,0 +1,56 @@
+mod common;
+
+use fup_client::{Client, CreateOptions, Upload};
+use common::server;
+
+/// Default `max_lifetime` of the server
+const MAX_LIFETIME: u64 = 90 * 24 * 60 * 60;
+
+fn client() -> Client {
+    Client::new(server()).unwrap()
+}
+
+#[actix_web::test]
+async fn create_is_limited_to_the_maximum_lifetime() {
+    let options = CreateOptions { expires_in: MAX_LIFETIME, ..CreateOptions::default() };
+    let created = client().create_session(vec![Upload::bytes("api.txt", "api")], &options).await.unwrap();
+    assert_eq!(created.files[0].name, "api.txt");
+
+    for expires_in in [MAX_LIFETIME + 1, u64::MAX] {
+        let options = CreateOptions { expires_in, ..CreateOptions::default() };
+        let err = client().create_session(vec![Upload::bytes("api.txt", "api")], &options).await.unwrap_err();
+        assert_eq!(err.status().map(|s| s.as_u16()), Some(400), "{expires_in}");
+        assert_eq!(err.code(), Some("invalid_upload"));
+    }
+}
+
+#[actix_web::test]
+async fn extend_refuses_overlong_lifetimes() {
+    let client = client();
+    let created = client.create_session(vec![Upload::bytes("a.txt", "a")], &CreateOptions::default()).await.unwrap();
+
+    for by in [u64::MAX, MAX_LIFETIME] {
+        let err = client.extend(&created.session, Some(&created.token), by).await.unwrap_err();
+        assert_eq!(err.status().map(|s| s.as_u16()), Some(400), "{by}");
+        assert_eq!(err.code(), Some("bad_request"));
+    }
+    assert_eq!(client.info(&created.session, None).await.unwrap().expires_at, created.expires_at);
+    let page = reqwest::get(format!("{}/f/session/{}", server(), created.session)).await.unwrap();
+    assert_eq!(page.status(), 200);
+
+    let expires_at = client.extend(&created.session, Some(&created.token), 3600).await.unwrap();
+    assert_eq!(expires_at, created.expires_at + 3600);
+}
+
+#[actix_web::test]
+async fn remove_needs_the_owner() {
+    let client = client();
+    let created = client.create_session(vec![Upload::bytes("a.txt", "a")], &CreateOptions::default()).await.unwrap();
+
+    let err = client.delete(&created.session, None).await.unwrap_err();
+    assert_eq!(err.code(), Some("forbidden"));
+    client.delete(&created.session, Some(&created.token)).await.unwrap();
+    assert_eq!(client.info(&created.session, None).await.unwrap_err().code(), Some("not_found"));
+    let err = client.delete(&created.session, Some(&created.token)).await.unwrap_err();
+    assert_eq!(err.status().map(|s| s.as_u16()), Some(404));
+}
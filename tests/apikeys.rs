@@ -0,0 +1,108 @@
+mod common;
+
+use std::process::{Command, Output};
+use fup_client::{Client, CreateOptions, Upload};
+use common::server;
+
+/// Runs the admin command in the server's directory
+fn admin(args: &[&str]) -> Output {
+    server();
+    Command::new(env!("CARGO_BIN_EXE_fileupload"))
+        .arg("apikey")
+        .args(args)
+        .current_dir(std::env::current_dir().unwrap())
+        .output()
+        .unwrap()
+}
+
+/// A client authenticating with a new key
+fn keyed_client(args: &[&str]) -> Client {
+    let output = admin(&[&["create", "tests"], args].concat());
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let token = String::from_utf8(output.stdout).unwrap().trim().to_string();
+    Client::new(server()).unwrap().with_api_key(token)
+}
+
+#[actix_web::test]
+async fn keys_apply_their_limits() {
+    let client = keyed_client(&["--max-files", "1", "--max-lifetime", "3600"]);
+    let options = CreateOptions { expires_in: 3600, ..CreateOptions::default() };
+
+    let err = client.create_session(vec![Upload::bytes("a.txt", "a"), Upload::bytes("b.txt", "b")], &options)
+        .await.unwrap_err();
+    assert_eq!(err.code(), Some("invalid_upload"));
+    assert!(err.to_string().contains("Too many files"), "{err}");
+
+    let err = client.create_session(vec![Upload::bytes("a.txt", "a")], &CreateOptions::default()).await.unwrap_err();
+    assert!(err.to_string().contains("limit of the API key"), "{err}");
+
+    client.create_session(vec![Upload::bytes("a.txt", "a")], &options).await.unwrap();
+}
+
+#[actix_web::test]
+async fn keys_manage_their_own_sessions() {
+    let client = keyed_client(&[]);
+    let first = client.create_session(vec![Upload::bytes("one.txt", "1")], &CreateOptions::default()).await.unwrap();
+    let second = client.create_session(vec![Upload::bytes("two.txt", "2")], &CreateOptions::default()).await.unwrap();
+    let other = Client::new(server()).unwrap()
+        .create_session(vec![Upload::bytes("other.txt", "3")], &CreateOptions::default()).await.unwrap();
+
+    let sessions: Vec<String> = client.key_sessions().await.unwrap().into_iter().map(|s| s.session).collect();
+    assert_eq!(sessions.len(), 2);
+    assert!(sessions.contains(&first.session) && sessions.contains(&second.session));
+    assert!(client.info(&first.session, None).await.unwrap().owner);
+    assert!(!client.info(&other.session, None).await.unwrap().owner);
+
+    client.delete(&first.session, None).await.unwrap();
+    assert_eq!(client.delete(&other.session, None).await.unwrap_err().code(), Some("forbidden"));
+    assert_eq!(client.delete_key_sessions().await.unwrap(), 1);
+    assert!(client.key_sessions().await.unwrap().is_empty());
+    assert!(client.info(&other.session, None).await.is_ok());
+}
+
+#[actix_web::test]
+async fn unknown_keys_are_refused() {
+    let client = Client::new(server()).unwrap().with_api_key("fup_nokey123_wrongsecret");
+    let err = client.key_sessions().await.unwrap_err();
+    assert_eq!(err.status().map(|s| s.as_u16()), Some(401));
+    assert_eq!(err.code(), Some("invalid_api_key"));
+    let err = client.create_session(vec![Upload::bytes("a.txt", "a")], &CreateOptions::default()).await.unwrap_err();
+    assert_eq!(err.code(), Some("invalid_api_key"));
+
+    let err = Client::new(server()).unwrap().key_sessions().await.unwrap_err();
+    assert_eq!(err.status().map(|s| s.as_u16()), Some(401));
+}
+
+#[actix_web::test]
+async fn deleted_keys_are_refused_right_away() {
+    let output = admin(&["create", "short-lived"]);
+    let token = String::from_utf8(output.stdout).unwrap().trim().to_string();
+    let id = token.split('_').nth(1).unwrap().to_string();
+    let client = Client::new(server()).unwrap().with_api_key(&token);
+    assert!(client.key_sessions().await.unwrap().is_empty());
+
+    assert!(admin(&["delete", &id]).status.success());
+    assert_eq!(client.key_sessions().await.unwrap_err().code(), Some("invalid_api_key"));
+}
+
+#[test]
+fn usage_errors_fail() {
+    for args in [&["bogus"][..], &["create", "x", "--max-files"], &["create", "x", "--max-files", "many"]] {
+        let output = admin(args);
+        assert_eq!(output.status.code(), Some(2), "{args:?}");
+        assert!(String::from_utf8_lossy(&output.stderr).contains("Usage:"));
+    }
+}
+
+#[test]
+fn concurrent_commands_keep_every_key() {
+    let handles: Vec<_> = (0..8).map(|i| std::thread::spawn(move || admin(&["create", &format!("parallel-{i}")]))).collect();
+    for handle in handles {
+        let output = handle.join().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    }
+    let list = String::from_utf8(admin(&["list"]).stdout).unwrap();
+    for i in 0..8 {
+        assert!(list.contains(&format!("parallel-{i} ")), "parallel-{i} missing in {list}");
+    }
+}
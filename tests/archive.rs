@@ -0,0 +1,95 @@
+mod common;
+
+use std::io::{Cursor, Write};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use fup_client::{Client, CreateOptions, Error, SessionCreated, Upload};
+use zip::write::SimpleFileOptions;
+use common::server;
+
+fn zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut zip = zip::ZipWriter::new(Cursor::new(vec![]));
+    for (name, content) in entries {
+        zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+        zip.write_all(content).unwrap();
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+fn tar_gz(entries: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut tar = tar::Builder::new(GzEncoder::new(vec![], Compression::default()));
+    for (name, content) in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        tar.append_data(&mut header, name, *content).unwrap();
+    }
+    tar.into_inner().unwrap().finish().unwrap()
+}
+
+async fn upload(name: &str, archive: Vec<u8>, extract: bool) -> Result<SessionCreated, Error> {
+    let options = CreateOptions { extract, ..CreateOptions::default() };
+    Client::new(server()).unwrap().create_session(vec![Upload::bytes(name, archive)], &options).await
+}
+
+fn names(created: &SessionCreated) -> Vec<&str> {
+    created.files.iter().map(|f| f.name.as_str()).collect()
+}
+
+#[actix_web::test]
+async fn extracts_zip_and_tar_archives() {
+    let created = upload("bundle.zip", zip(&[("a.txt", b"a"), ("dir/b.txt", b"bb")]), true).await.unwrap();
+    assert_eq!(names(&created), ["bundle/a.txt", "bundle/dir/b.txt"]);
+    assert_eq!(created.files[1].size, 2);
+
+    let created = upload("release.tar.gz", tar_gz(&[("bin/tool", b"binary"), ("README", b"read me")]), true).await.unwrap();
+    assert_eq!(names(&created), ["release/README", "release/bin/tool"]);
+
+    let created = upload("kept.zip", zip(&[("a.txt", b"a")]), false).await.unwrap();
+    assert_eq!(names(&created), ["kept.zip"]);
+}
+
+#[actix_web::test]
+async fn browses_archives_without_extracting() {
+    let created = upload("bundle.zip", zip(&[("inner/<notes>.txt", b"notes")]), false).await.unwrap();
+    let page = reqwest::get(format!("{}/f/browse/{}/bundle.zip", server(), created.session)).await.unwrap();
+    assert_eq!(page.status(), 200);
+    let page = page.text().await.unwrap();
+    assert!(page.contains("inner/&lt;notes&gt;.txt"), "{page}");
+
+    let missing = reqwest::get(format!("{}/f/browse/{}/other.zip", server(), created.session)).await.unwrap();
+    assert_eq!(missing.status(), 404);
+}
+
+#[actix_web::test]
+async fn refuses_dangerous_archives() {
+    let zeros = vec![0; 2 * 1024 * 1024];
+    let bomb = upload("bomb.zip", zip(&[("zeros", &zeros)]), true).await.unwrap_err();
+    assert_eq!(bomb.status().map(|s| s.as_u16()), Some(400));
+    assert!(bomb.to_string().contains("compressed suspiciously well"), "{bomb}");
+
+    let entries: Vec<(String, &[u8])> = (0..11).map(|i| (format!("{i}.txt"), &b"x"[..])).collect();
+    let entries: Vec<(&str, &[u8])> = entries.iter().map(|(name, content)| (name.as_str(), *content)).collect();
+    let many = upload("many.zip", zip(&entries), true).await.unwrap_err();
+    assert!(many.to_string().contains("too many files"), "{many}");
+
+    let escape = upload("escape.zip", zip(&[("../../escape.txt", b"x")]), true).await.unwrap_err();
+    assert!(escape.to_string().contains("invalid or conflicting path"), "{escape}");
+}
+
+#[actix_web::test]
+async fn refuses_archives_named_like_reserved_files() {
+    for name in [".meta.zip", ".token.tar.gz", "...zip", ".zip"] {
+        let err = upload(name, zip(&[("a.txt", b"a")]), true).await.unwrap_err();
+        assert_eq!(err.status().map(|s| s.as_u16()), Some(400), "{name}");
+        assert!(err.to_string().contains("not a valid folder name"), "{name}: {err}");
+    }
+}
+
+#[actix_web::test]
+async fn browsing_caps_decompression() {
+    let zeros = vec![0; 2 * 1024 * 1024];
+    let created = upload("zeros.tar.gz", tar_gz(&[("zeros", &zeros)]), false).await.unwrap();
+    let page = reqwest::get(format!("{}/f/browse/{}/zeros.tar.gz", server(), created.session)).await.unwrap();
+    assert_eq!(page.status(), 400);
+}
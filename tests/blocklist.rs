@@ -0,0 +1,82 @@
+mod common;
+
+use std::io::{Cursor, Write};
+use std::process::{Command, Output};
+use fup_client::{Client, CreateOptions, Upload};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+use common::server;
+
+fn run(args: &[&str]) -> Output {
+    server();
+    Command::new(env!("CARGO_BIN_EXE_fileupload"))
+        .arg("blocklist")
+        .args(args)
+        .current_dir(std::env::current_dir().unwrap())
+        .output()
+        .unwrap()
+}
+
+/// Runs the admin command in the server's directory, returns its stdout
+fn admin(args: &[&str]) -> String {
+    let output = run(args);
+    assert!(output.status.success(), "blocklist {args:?} failed: {}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[actix_web::test]
+async fn blocked_hashes_are_refused() {
+    let client = Client::new(server()).unwrap();
+    let content = "the same takedown material, again";
+    let first = client.create_session(vec![
+        Upload::bytes("docs/abuse.txt", content),
+        Upload::bytes("other.txt", "unrelated"),
+    ], &CreateOptions::default()).await.unwrap();
+    let sha256 = first.files.iter().find(|f| f.name == "docs/abuse.txt").unwrap().sha256.clone().unwrap();
+
+    let added = admin(&["add-session", &first.session, "docs/abuse.txt", "--reason", "takedown"]);
+    assert_eq!(added, format!("Blocked {sha256}  docs/abuse.txt\n"));
+    let listed = admin(&["list"]);
+    assert!(listed.contains(&format!("{sha256} ")), "{listed}");
+    assert!(listed.contains(&format!("{}/docs/abuse.txt takedown", first.session)), "{listed}");
+
+    let err = client.create_session(vec![
+        Upload::bytes("fine.txt", "fine"),
+        Upload::bytes("renamed.txt", content),
+    ], &CreateOptions::default()).await.unwrap_err();
+    assert_eq!(err.status().map(|s| s.as_u16()), Some(403));
+    assert_eq!(err.code(), Some("blocked_content"));
+    assert!(err.to_string().contains("renamed.txt"), "{err}");
+
+    assert_eq!(admin(&["remove", &sha256]), format!("Removed {sha256}\n"));
+    client.create_session(vec![Upload::bytes("renamed.txt", content)], &CreateOptions::default()).await.unwrap();
+}
+
+#[actix_web::test]
+async fn archive_contents_are_checked() {
+    let client = Client::new(server()).unwrap();
+    let content = "blocked inside an archive";
+    let created = client.create_session(vec![Upload::bytes("payload.txt", content)], &CreateOptions::default()).await.unwrap();
+    admin(&["add-session", &created.session]);
+
+    let mut zip = ZipWriter::new(Cursor::new(vec![]));
+    zip.start_file("inner/payload.txt", SimpleFileOptions::default()).unwrap();
+    zip.write_all(content.as_bytes()).unwrap();
+    let zip = zip.finish().unwrap().into_inner();
+
+    let options = CreateOptions { extract: true, ..CreateOptions::default() };
+    let err = client.create_session(vec![Upload::bytes("bundle.zip", zip)], &options).await.unwrap_err();
+    assert_eq!(err.code(), Some("blocked_content"));
+    assert!(err.to_string().contains("bundle/inner/payload.txt"), "{err}");
+}
+
+#[test]
+fn usage_errors_fail() {
+    for args in [&["bogus"][..], &["add"], &["add", "--reason"], &["list", "--force"]] {
+        let output = run(args);
+        assert_eq!(output.status.code(), Some(2), "{args:?}");
+        assert!(String::from_utf8_lossy(&output.stderr).contains("Usage:"));
+    }
+    let output = run(&["add", "nothex"]);
+    assert!(!output.status.success());
+}
@@ -0,0 +1,53 @@
+mod common;
+
+use std::io::{Cursor, Read};
+use fup_client::{Client, CreateOptions, Upload};
+use zip::ZipArchive;
+use common::server;
+
+const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+#[actix_web::test]
+async fn checksums_are_recorded_and_served() {
+    let client = Client::new(server()).unwrap();
+    let created = client.create_session(vec![
+        Upload::bytes("hello.txt", "hello"),
+        Upload::bytes("docs/empty.txt", ""),
+    ], &CreateOptions::default()).await.unwrap();
+    let hello = created.files.iter().find(|f| f.name == "hello.txt").unwrap();
+    assert_eq!(hello.sha256.as_deref(), Some(HELLO_SHA256));
+    assert_eq!(hello.blake3, None);
+
+    let res = reqwest::get(format!("{}/f/download/{}/hello.txt", server(), created.session)).await.unwrap();
+    assert_eq!(res.headers()["repr-digest"], "sha-256=:LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=:");
+    assert_eq!(res.headers()["digest"], "SHA-256=LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=");
+
+    let info = reqwest::Client::new().get(format!("{}/f/get-info", server()))
+        .header("session", &created.session)
+        .header("checksums", "true")
+        .send().await.unwrap().text().await.unwrap();
+    assert!(info.lines().any(|line| line == format!("5 {HELLO_SHA256} hello.txt")), "{info}");
+
+    let zip = client.download_all(&created.session, None).await.unwrap().bytes().await.unwrap();
+    let mut zip = ZipArchive::new(Cursor::new(zip)).unwrap();
+    let mut sums = String::new();
+    zip.by_name("SHA256SUMS").unwrap().read_to_string(&mut sums).unwrap();
+    assert!(sums.contains(&format!("{HELLO_SHA256}  hello.txt\n")), "{sums}");
+    assert!(sums.contains("  docs/empty.txt\n"));
+}
+
+#[actix_web::test]
+async fn verify_compares_with_the_stored_file() {
+    let created = Client::new(server()).unwrap()
+        .create_session(vec![Upload::bytes("hello.txt", "hello")], &CreateOptions::default()).await.unwrap();
+    let verify = |sha256: Option<&'static str>| {
+        let mut req = reqwest::Client::new().get(format!("{}/f/verify/{}/hello.txt", server(), created.session));
+        if let Some(sha256) = sha256 {
+            req = req.header("sha256", sha256);
+        }
+        async move { req.send().await.unwrap().status().as_u16() }
+    };
+    assert_eq!(verify(Some(HELLO_SHA256)).await, 200);
+    assert_eq!(verify(Some("00")).await, 409);
+    assert_eq!(verify(None).await, 400);
+}
@@ -0,0 +1,144 @@
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use tempfile::TempDir;
+use common::server;
+
+/// A client with its own config file
+struct Client {
+    dir: TempDir,
+}
+
+impl Client {
+    fn new() -> Client {
+        server();
+        Client { dir: TempDir::new().unwrap() }
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.path().join(name)
+    }
+
+    fn run(&self, args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_fup"))
+            .args(["--server", server()])
+            .args(args)
+            .env("FUP_CONFIG", self.path("config.json"))
+            .output()
+            .unwrap()
+    }
+
+    /// Runs a command that has to succeed and returns its stdout
+    fn ok(&self, args: &[&str]) -> String {
+        let output = self.run(args);
+        assert!(output.status.success(), "fup {args:?} failed: {}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    fn push(&self, args: &[&str]) -> String {
+        let url = self.ok(&[&["push"], args].concat());
+        url.trim().rsplit('/').next().unwrap().to_string()
+    }
+}
+
+fn write(path: &Path, content: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+#[test]
+fn push_and_pull_files_and_directories() {
+    let client = Client::new();
+    write(&client.path("upload/a.txt"), "first file");
+    write(&client.path("upload/nested/b.txt"), "second file");
+    write(&client.path("single.txt"), "third file");
+
+    let session = client.push(&[
+        client.path("upload").to_str().unwrap(),
+        client.path("single.txt").to_str().unwrap(),
+    ]);
+    let info = client.ok(&["info", &session]);
+    for name in ["upload/a.txt", "upload/nested/b.txt", "single.txt"] {
+        assert!(info.contains(name), "{name} missing in {info}");
+    }
+
+    let out = client.path("out");
+    client.ok(&["pull", &session, "-o", out.to_str().unwrap()]);
+    assert_eq!(fs::read_to_string(out.join("upload/a.txt")).unwrap(), "first file");
+    assert_eq!(fs::read_to_string(out.join("upload/nested/b.txt")).unwrap(), "second file");
+    assert_eq!(fs::read_to_string(out.join("single.txt")).unwrap(), "third file");
+
+    let one = client.path("one");
+    client.ok(&["pull", &session, "upload/nested/b.txt", "-o", one.to_str().unwrap()]);
+    assert_eq!(fs::read_to_string(one.join("upload/nested/b.txt")).unwrap(), "second file");
+    assert_eq!(fs::read_dir(&one).unwrap().count(), 1);
+}
+
+#[test]
+fn list_extend_and_remove() {
+    let client = Client::new();
+    write(&client.path("file.txt"), "content");
+    let session = client.push(&["--expires", "10m", client.path("file.txt").to_str().unwrap()]);
+    assert!(client.ok(&["ls"]).contains(&session));
+
+    let extended = client.ok(&["extend", &session, "2d"]);
+    assert!(extended.contains("in 2d"), "{extended}");
+
+    // Only the owner may delete
+    assert!(!Client::new().run(&["rm", &session]).status.success());
+    client.ok(&["rm", &session]);
+    assert!(!client.ok(&["ls"]).contains(&session));
+    assert!(!client.run(&["info", &session]).status.success());
+}
+
+#[test]
+fn signed_only_sessions_need_the_owner_token() {
+    let client = Client::new();
+    write(&client.path("secret.txt"), "signed content");
+    let session = client.push(&["--signed-only", client.path("secret.txt").to_str().unwrap()]);
+
+    let out = client.path("out");
+    client.ok(&["pull", &session, "-o", out.to_str().unwrap()]);
+    assert_eq!(fs::read_to_string(out.join("secret.txt")).unwrap(), "signed content");
+
+    let stranger = Client::new();
+    let output = stranger.run(&["pull", &session, "-o", stranger.path("out").to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(!stranger.path("out/secret.txt").exists());
+}
+
+#[test]
+fn pulls_skip_quarantined_files() {
+    let client = Client::new();
+    write(&client.path("upload/readme.txt"), "fine");
+    // An ELF header, which the test server quarantines
+    let mut elf = b"\x7fELF\x02\x01\x01".to_vec();
+    elf.resize(64, 0);
+    fs::write(client.path("upload/tool"), elf).unwrap();
+    let session = client.push(&[client.path("upload").to_str().unwrap()]);
+
+    let out = client.path("out");
+    let output = client.run(&["pull", &session, "-o", out.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Skipping upload/tool"));
+    assert_eq!(fs::read_to_string(out.join("upload/readme.txt")).unwrap(), "fine");
+    assert!(!out.join("upload/tool").exists());
+
+    let output = client.run(&["pull", &session, "upload/tool", "-o", client.path("one").to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("quarantined"));
+}
+
+#[test]
+fn invalid_durations_are_refused() {
+    let client = Client::new();
+    write(&client.path("file.txt"), "content");
+    for expires in ["soon", "1w", "1.5h", "213503982334602d"] {
+        let output = client.run(&["push", "--expires", expires, client.path("file.txt").to_str().unwrap()]);
+        assert!(!output.status.success(), "{expires}");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("Invalid duration"), "{expires}: {stderr}");
+    }
+}
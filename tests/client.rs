@@ -0,0 +1,100 @@
+mod common;
+
+use fup_client::{Client, CreateOptions, Error, Upload};
+use common::server;
+
+fn client() -> Client {
+    Client::new(server()).unwrap()
+}
+
+#[actix_web::test]
+async fn create_inspect_and_download() {
+    let client = client();
+    let created = client.create_session(vec![
+        Upload::bytes("a.txt", "first"),
+        Upload::bytes("b.txt", "second one"),
+    ], &CreateOptions::default()).await.unwrap();
+    assert_eq!(created.files.len(), 2);
+
+    let info = client.info(&created.session, None).await.unwrap();
+    assert!(!info.owner);
+    assert_eq!(info.files.iter().map(|f| (f.name.as_str(), f.size)).collect::<Vec<_>>(), [("a.txt", 5), ("b.txt", 10)]);
+    assert!(client.info(&created.session, Some(&created.token)).await.unwrap().owner);
+
+    let body = client.download(&created.session, "b.txt", None).await.unwrap().bytes().await.unwrap();
+    assert_eq!(&body[..], b"second one");
+    let zip = client.download_all(&created.session, None).await.unwrap().bytes().await.unwrap();
+    assert!(zip.starts_with(b"PK"));
+}
+
+#[actix_web::test]
+async fn raw_upload() {
+    let client = client();
+    let uploaded = client.upload(Upload::bytes("notes.txt", "raw body"), &CreateOptions::default()).await.unwrap();
+    assert!(uploaded.url.ends_with(&format!("/f/download/{}/notes.txt", uploaded.session)), "{}", uploaded.url);
+    let files = client.files(&uploaded.session, None).await.unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].name, "notes.txt");
+}
+
+#[actix_web::test]
+async fn owner_actions_need_the_token() {
+    let client = client();
+    let created = client.create_session(vec![Upload::bytes("a.txt", "content")], &CreateOptions::default())
+        .await.unwrap();
+
+    let err = client.delete(&created.session, Some("wrong")).await.unwrap_err();
+    assert_eq!(err.status().map(|s| s.as_u16()), Some(403));
+    assert_eq!(err.code(), Some("forbidden"));
+
+    let expires_at = client.extend(&created.session, Some(&created.token), 60).await.unwrap();
+    assert_eq!(expires_at, created.expires_at + 60);
+
+    client.delete(&created.session, Some(&created.token)).await.unwrap();
+    let err = client.info(&created.session, None).await.unwrap_err();
+    assert!(matches!(err, Error::Api { status, .. } if status.as_u16() == 404));
+}
+
+#[actix_web::test]
+async fn signed_only_downloads_need_a_link() {
+    let client = client();
+    let options = CreateOptions { signed_only: true, ..CreateOptions::default() };
+    let created = client.create_session(vec![Upload::bytes("secret.txt", "signed")], &options).await.unwrap();
+
+    let err = client.download(&created.session, "secret.txt", None).await.err().unwrap();
+    assert_eq!(err.status().map(|s| s.as_u16()), Some(403));
+    assert_eq!(err.code(), None);
+
+    let link = client.sign(&created.session, &created.token, Some("secret.txt"), Some(60)).await.unwrap();
+    let body = client.download(&created.session, "secret.txt", Some(&link)).await.unwrap().bytes().await.unwrap();
+    assert_eq!(&body[..], b"signed");
+}
+
+#[actix_web::test]
+async fn directories_keep_their_structure() {
+    let client = client();
+    let created = client.create_session(vec![
+        Upload::bytes("docs/readme.md", "top"),
+        Upload::bytes("docs\\guide\\./intro.md", "nested"),
+        Upload::bytes("root.txt", "root"),
+    ], &CreateOptions::default()).await.unwrap();
+    let names: Vec<String> = created.files.iter().map(|f| f.name.clone()).collect();
+    assert_eq!(names, ["docs/guide/intro.md", "docs/readme.md", "root.txt"]);
+
+    let body = client.download(&created.session, "docs/guide/intro.md", None).await.unwrap().bytes().await.unwrap();
+    assert_eq!(&body[..], b"nested");
+    let zip = client.download_all(&created.session, None).await.unwrap().bytes().await.unwrap();
+    assert!(zip.windows(19).any(|name| name == b"docs/guide/intro.md"));
+    let err = client.download(&created.session, "docs", None).await.err().unwrap();
+    assert_eq!(err.status().map(|s| s.as_u16()), Some(404));
+
+    for name in ["../escape.txt", "docs/../../escape.txt", "docs/.token"] {
+        let err = client.create_session(vec![Upload::bytes(name, "x")], &CreateOptions::default()).await.unwrap_err();
+        assert_eq!(err.status().map(|s| s.as_u16()), Some(400), "{name}");
+    }
+    let err = client.create_session(vec![
+        Upload::bytes("clash", "file"),
+        Upload::bytes("clash/inner.txt", "below"),
+    ], &CreateOptions::default()).await.unwrap_err();
+    assert_eq!(err.status().map(|s| s.as_u16()), Some(400));
+}
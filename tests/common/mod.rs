@@ -0,0 +1,43 @@
+// Each test binary uses a part of the helpers
+#![allow(dead_code)]
+
+use std::fs;
+use std::sync::{mpsc, OnceLock};
+use std::thread;
+use actix_web::HttpServer;
+use tempfile::TempDir;
+
+/// `fup.toml` of the shared server
+const CONFIG: &str = "trusted_proxies = [\"127.0.0.1\"]\n\n\
+    [rate_limit]\nenabled = false\n\n\
+    [content_policy]\ndeny = [\"application/x-mach-binary\"]\nquarantine = [\"application/x-executable\"]\n\n\
+    [compression]\nenabled = true\n\n\
+    [scan]\nclamd = \"unix:clamd.sock\"\naction = \"quarantine\"\ntimeout_secs = 5\n\n\
+    [abuse]\nauto_disable_after = 2\nadmin_user = \"admin\"\n\
+    admin_password_hash = \"pbkdf2-sha256$1000$6675707465737473a1b2c3d4e5f60718$\
+    389404c3b4fbb3c3418da64044fe299cfa4f7e069960dab946f1d6b2aae7e9af\"\n";
+
+/// Base URL of a server shared by all tests, running on a thread with a temporary working directory
+pub fn server() -> &'static str {
+    static SERVER: OnceLock<String> = OnceLock::new();
+    SERVER.get_or_init(|| start(CONFIG))
+}
+
+/// Starts the server of this test binary with `config` as `fup.toml`. The configuration is read once per process,
+/// test files that need a different one call this instead of `server` from their own `OnceLock`.
+pub fn start(config: &str) -> String {
+    let dir = TempDir::new().unwrap().keep();
+    fs::write(dir.join("fup.toml"), config).unwrap();
+    std::env::set_current_dir(&dir).unwrap();
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        actix_web::rt::System::new().block_on(async move {
+            fileupload::prepare().unwrap();
+            let server = HttpServer::new(fileupload::app).workers(2).bind(("127.0.0.1", 0)).unwrap();
+            tx.send(server.addrs()[0]).unwrap();
+            server.run().await.unwrap();
+        })
+    });
+    format!("http://{}", rx.recv().unwrap())
+}
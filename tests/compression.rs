@@ -0,0 +1,129 @@
+mod common;
+
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::PathBuf;
+use fup_client::{Client, CreateOptions, Upload};
+use sha2::{Digest, Sha256};
+use zip::ZipArchive;
+use common::server;
+
+fn log() -> String {
+    (0..2000).map(|i| format!("2026-10-19 12:00:{:02} INFO request {i} served\n", i % 60)).collect()
+}
+
+#[actix_web::test]
+async fn text_is_stored_compressed_and_served_either_way() {
+    let client = Client::new(server()).unwrap();
+    let log = log();
+    let created = client.create_session(vec![Upload::bytes("server.log", log.clone())], &CreateOptions::default()).await.unwrap();
+    let session = &created.session;
+    let sha256 = created.files[0].sha256.clone().unwrap();
+    assert_eq!(sha256, hex::encode(Sha256::digest(&log)));
+    assert_eq!(created.files[0].size, log.len() as u64);
+
+    let blob = PathBuf::from("blobs").join(&sha256[..2]).join(format!("{sha256}.zst"));
+    let stored = fs::read(&blob).unwrap();
+    assert!(stored.len() < log.len());
+    assert_eq!(zstd::decode_all(&stored[..]).unwrap(), log.as_bytes());
+
+    let url = format!("{}/f/download/{session}/server.log", server());
+    let res = reqwest::Client::new().get(&url).header("accept-encoding", "gzip, zstd").send().await.unwrap();
+    assert_eq!(res.headers()["content-encoding"], "zstd");
+    assert!(res.headers()["vary"].to_str().unwrap().contains("Accept-Encoding"));
+    assert!(!res.headers().contains_key("repr-digest"));
+    assert_eq!(zstd::decode_all(&res.bytes().await.unwrap()[..]).unwrap(), log.as_bytes());
+
+    let res = reqwest::get(&url).await.unwrap();
+    assert!(!res.headers().contains_key("content-encoding"));
+    assert!(res.headers().contains_key("repr-digest"));
+    assert_eq!(res.text().await.unwrap(), log);
+
+    let verify = reqwest::Client::new().get(format!("{}/f/verify/{session}/server.log", server()))
+        .header("sha256", &sha256).send().await.unwrap();
+    assert_eq!(verify.status(), 200);
+
+    let view = reqwest::get(format!("{}/f/view/{session}/server.log", server())).await.unwrap().text().await.unwrap();
+    assert!(view.contains("request 1999 served"));
+
+    let info = reqwest::Client::new().get(format!("{}/f/get-info", server()))
+        .header("session", session).send().await.unwrap().text().await.unwrap();
+    assert!(info.contains(&log.len().to_string()), "{info}");
+
+    let zip = client.download_all(session, None).await.unwrap().bytes().await.unwrap();
+    let mut zip = ZipArchive::new(Cursor::new(zip)).unwrap();
+    let mut content = String::new();
+    zip.by_name("server.log").unwrap().read_to_string(&mut content).unwrap();
+    assert_eq!(content, log);
+}
+
+#[actix_web::test]
+async fn small_and_binary_files_stay_uncompressed() {
+    let client = Client::new(server()).unwrap();
+    let created = client.create_session(vec![
+        Upload::bytes("note.txt", "short"),
+        Upload::bytes("data.bin", vec![0xffu8; 64 * 1024]),
+    ], &CreateOptions::default()).await.unwrap();
+    for file in &created.files {
+        let sha256 = file.sha256.clone().unwrap();
+        assert!(PathBuf::from("blobs").join(&sha256[..2]).join(&sha256).exists(), "{}", file.name);
+    }
+    let res = reqwest::Client::new().get(format!("{}/f/download/{}/note.txt", server(), created.session))
+        .header("accept-encoding", "zstd").send().await.unwrap();
+    assert!(!res.headers().contains_key("content-encoding"));
+}
+
+#[actix_web::test]
+async fn pages_are_compressed() {
+    let created = Client::new(server()).unwrap()
+        .create_session(vec![Upload::bytes("a.txt", "a")], &CreateOptions::default()).await.unwrap();
+    for path in [format!("/f/session/{}", created.session), "/r/style.css".to_string()] {
+        let res = reqwest::Client::new().get(format!("{}{path}", server()))
+            .header("accept-encoding", "gzip").send().await.unwrap();
+        assert_eq!(res.headers()["content-encoding"], "gzip", "{path}");
+    }
+}
+
+#[actix_web::test]
+async fn decoded_files_have_a_length_and_ranges() {
+    let log = log();
+    let created = Client::new(server()).unwrap()
+        .create_session(vec![Upload::bytes("ranged.log", log.clone())], &CreateOptions::default()).await.unwrap();
+    let url = format!("{}/f/download/{}/ranged.log", server(), created.session);
+
+    let res = reqwest::get(&url).await.unwrap();
+    assert_eq!(res.headers()["content-length"], log.len().to_string().as_str());
+    assert_eq!(res.headers()["accept-ranges"], "bytes");
+
+    let res = reqwest::Client::new().get(&url).header("range", "bytes=100-149").send().await.unwrap();
+    assert_eq!(res.status(), 206);
+    assert_eq!(res.headers()["content-range"], format!("bytes 100-149/{}", log.len()).as_str());
+    assert_eq!(res.text().await.unwrap(), &log[100..150]);
+
+    let preview = format!("{}/f/preview/{}/ranged.log", server(), created.session);
+    let res = reqwest::Client::new().get(&preview).header("range", "bytes=-10").send().await.unwrap();
+    assert_eq!(res.status(), 206);
+    assert_eq!(res.text().await.unwrap(), &log[log.len() - 10..]);
+
+    let res = reqwest::Client::new().get(&url).header("range", format!("bytes={}-", log.len() + 5)).send().await.unwrap();
+    assert_eq!(res.status(), 416);
+}
+
+#[actix_web::test]
+async fn stored_files_honour_ranges_too() {
+    // Below the size worth compressing
+    let content: String = (0..100).map(|i| char::from(b'a' + i % 26)).collect();
+    let created = Client::new(server()).unwrap()
+        .create_session(vec![Upload::bytes("plain.txt", content.clone())], &CreateOptions::default()).await.unwrap();
+    let url = format!("{}/f/download/{}/plain.txt", server(), created.session);
+
+    let res = reqwest::Client::new().get(&url).header("range", "bytes=10-19").send().await.unwrap();
+    assert_eq!(res.status(), 206);
+    assert_eq!(res.headers()["content-range"], "bytes 10-19/100");
+    assert!(res.headers()["content-disposition"].to_str().unwrap().starts_with("attachment"));
+    assert!(res.headers().contains_key("repr-digest"));
+    assert_eq!(res.text().await.unwrap(), &content[10..20]);
+
+    let res = reqwest::Client::new().get(&url).header("range", "bytes=200-").send().await.unwrap();
+    assert_eq!(res.status(), 416);
+}
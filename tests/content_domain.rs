@@ -0,0 +1,95 @@
+mod common;
+
+use std::sync::OnceLock;
+use fup_client::{Client, CreateOptions, SessionCreated, Upload};
+use reqwest::header::{HOST, LOCATION};
+use reqwest::redirect::Policy;
+use reqwest::Url;
+
+const CONTENT_DOMAIN: &str = "usercontent.example.net";
+
+/// Uploads are served from their own host, reached through the test server by its `Host` header.
+/// No proxy is trusted, forwarded host headers must not count.
+fn server() -> &'static str {
+    static SERVER: OnceLock<String> = OnceLock::new();
+    SERVER.get_or_init(|| common::start("content_domain = \"usercontent.example.net\"\n\n\
+        [rate_limit]\nenabled = false\n"))
+}
+
+async fn upload(signed_only: bool) -> SessionCreated {
+    let options = CreateOptions { signed_only, ..CreateOptions::default() };
+    Client::new(server()).unwrap()
+        .create_session(vec![Upload::bytes("photo.png", "not a png")], &options).await.unwrap()
+}
+
+fn client() -> reqwest::Client {
+    reqwest::Client::builder().redirect(Policy::none()).build().unwrap()
+}
+
+/// `path_and_query` as asked of the content domain
+async fn from_content_host(path_and_query: &str) -> reqwest::Response {
+    client().get(format!("{}{path_and_query}", server())).header(HOST, CONTENT_DOMAIN).send().await.unwrap()
+}
+
+/// The redirect the main host answers `path` with, as the path and query to follow on the content domain
+async fn redirect(path: &str) -> String {
+    let res = client().get(format!("{}{path}", server())).send().await.unwrap();
+    assert_eq!(res.status(), 307, "{path}");
+    let location = Url::parse(res.headers()[LOCATION].to_str().unwrap()).unwrap();
+    assert_eq!(location.host_str(), Some(CONTENT_DOMAIN));
+    assert_eq!(location.path(), path.split('?').next().unwrap());
+    format!("{}?{}", location.path(), location.query().unwrap())
+}
+
+#[actix_web::test]
+async fn main_host_redirects_downloads() {
+    let created = upload(false).await;
+    for path in [
+        format!("/f/download/{}/photo.png", created.session),
+        format!("/f/download/{}", created.session),
+        format!("/f/preview/{}/photo.png", created.session),
+    ] {
+        let target = redirect(&path).await;
+        assert_eq!(from_content_host(&target).await.status(), 200, "{target}");
+        // The content domain only serves what the main host signed
+        assert_eq!(from_content_host(&path).await.status(), 403, "{path}");
+    }
+    let file_link = redirect(&format!("/f/download/{}/photo.png", created.session)).await;
+    let res = from_content_host(&file_link).await;
+    assert_eq!(res.text().await.unwrap(), "not a png");
+}
+
+#[actix_web::test]
+async fn signed_only_sessions_are_not_redirected_without_a_link() {
+    let created = upload(true).await;
+    let path = format!("/f/download/{}/photo.png", created.session);
+    let res = client().get(format!("{}{path}", server())).send().await.unwrap();
+    assert_eq!(res.status(), 403);
+
+    let link = Client::new(server()).unwrap().sign(&created.session, &created.token, Some("photo.png"), None).await.unwrap();
+    let target = redirect(&format!("{path}?{}", link.query().unwrap())).await;
+    assert_eq!(from_content_host(&target).await.status(), 200);
+}
+
+#[actix_web::test]
+async fn content_host_serves_nothing_else() {
+    let created = upload(false).await;
+    for path in ["/".to_string(), "/f/index".to_string(), format!("/f/session/{}", created.session), "/api/v1/openapi.json".to_string()] {
+        assert_eq!(from_content_host(&path).await.status(), 404, "{path}");
+    }
+    let res = client().get(format!("{}/f/session/{}", server(), created.session)).send().await.unwrap();
+    assert_eq!(res.status(), 200);
+}
+
+#[actix_web::test]
+async fn forwarded_hosts_need_a_trusted_proxy() {
+    let created = upload(false).await;
+    let path = format!("/f/download/{}/photo.png", created.session);
+    for (name, value) in [("x-forwarded-host", CONTENT_DOMAIN.to_string()), ("forwarded", format!("host={CONTENT_DOMAIN}"))] {
+        // Still the main host, which redirects instead of serving
+        let res = client().get(format!("{}{path}", server())).header(name, &value).send().await.unwrap();
+        assert_eq!(res.status(), 307, "{name}");
+        let res = client().get(format!("{}/", server())).header(name, &value).send().await.unwrap();
+        assert_eq!(res.status(), 200, "{name}");
+    }
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use fup_client::{Client, CreateOptions, Upload};
+use reqwest::header::CONTENT_TYPE;
+use common::server;
+
+/// Long enough for the ELF matcher
+const ELF: &[u8] = b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0\x02\0\x3e\0\x01\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0";
+const MACH_O: &[u8] = b"\xcf\xfa\xed\xfe\x07\0\0\x01";
+
+async fn download(session: &str, file: &str) -> reqwest::Response {
+    reqwest::get(format!("{}/f/download/{session}/{file}", server())).await.unwrap()
+}
+
+#[actix_web::test]
+async fn types_are_detected_from_content() {
+    let client = Client::new(server()).unwrap();
+    let created = client.create_session(vec![
+        Upload::bytes("image.txt", &b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR"[..]),
+        Upload::bytes("notes.bin", "plain words"),
+    ], &CreateOptions::default()).await.unwrap();
+
+    let types: Vec<_> = created.files.iter().map(|f| f.content_type.as_deref()).collect();
+    assert_eq!(types, [Some("image/png"), Some("text/plain")]);
+    let res = download(&created.session, "image.txt").await;
+    assert_eq!(res.headers()[CONTENT_TYPE], "image/png");
+}
+
+#[actix_web::test]
+async fn policy_rejects_and_quarantines() {
+    let client = Client::new(server()).unwrap();
+    let err = client.create_session(vec![
+        Upload::bytes("readme.txt", "fine"),
+        Upload::bytes("tool", MACH_O),
+    ], &CreateOptions::default()).await.unwrap_err();
+    assert_eq!(err.status().map(|s| s.as_u16()), Some(415));
+    assert_eq!(err.code(), Some("content_type_rejected"));
+
+    let created = client.create_session(vec![
+        Upload::bytes("readme.txt", "fine"),
+        Upload::bytes("tool", ELF),
+    ], &CreateOptions::default()).await.unwrap();
+    let tool = created.files.iter().find(|f| f.name == "tool").unwrap();
+    assert!(tool.quarantined);
+    assert_eq!(download(&created.session, "tool").await.status(), 403);
+    assert_eq!(download(&created.session, "readme.txt").await.status(), 200);
+    let verify = reqwest::Client::new().get(format!("{}/f/verify/{}/tool", server(), created.session))
+        .header("sha256", tool.sha256.as_deref().unwrap())
+        .send().await.unwrap();
+    assert_eq!(verify.status(), 403);
+
+    let zip = client.download_all(&created.session, None).await.unwrap().bytes().await.unwrap();
+    assert!(zip.windows(10).any(|name| name == b"readme.txt"));
+    assert!(!zip.windows(4).any(|name| name == b"tool"));
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use std::sync::OnceLock;
+use reqwest::header::{ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, ORIGIN};
+use reqwest::Method;
+
+const APP: &str = "https://app.example.org";
+const OTHER: &str = "https://evil.example.com";
+
+/// The API answers a single front end, the pages keep the default policy
+fn server() -> &'static str {
+    static SERVER: OnceLock<String> = OnceLock::new();
+    SERVER.get_or_init(|| common::start("[rate_limit]\nenabled = false\n\n\
+        [cors.api]\nallowed_origins = [\"https://app.example.org\"]\n"))
+}
+
+async fn preflight(path: &str, origin: &str, method: &str) -> reqwest::Response {
+    reqwest::Client::new().request(Method::OPTIONS, format!("{}{path}", server()))
+        .header(ORIGIN, origin)
+        .header("access-control-request-method", method)
+        .send().await.unwrap()
+}
+
+async fn get(path: &str, origin: &str) -> reqwest::Response {
+    reqwest::Client::new().get(format!("{}{path}", server()))
+        .header(ORIGIN, origin)
+        .send().await.unwrap()
+}
+
+fn header<'a>(res: &'a reqwest::Response, name: &reqwest::header::HeaderName) -> Option<&'a str> {
+    res.headers().get(name).map(|v| v.to_str().unwrap())
+}
+
+#[actix_web::test]
+async fn api_answers_the_configured_origins() {
+    for path in ["/api/v1/sessions", "/f/upload"] {
+        let res = preflight(path, APP, "POST").await;
+        assert!(res.status().is_success(), "{path}: {}", res.status());
+        assert_eq!(header(&res, &ACCESS_CONTROL_ALLOW_ORIGIN), Some(APP));
+        assert!(header(&res, &ACCESS_CONTROL_ALLOW_METHODS).unwrap().contains("POST"));
+
+        let res = preflight(path, OTHER, "POST").await;
+        assert!(!res.status().is_success(), "{path}: {}", res.status());
+        assert_eq!(header(&res, &ACCESS_CONTROL_ALLOW_ORIGIN), None);
+    }
+
+    let res = get("/f/index", APP).await;
+    assert_eq!(header(&res, &ACCESS_CONTROL_ALLOW_ORIGIN), Some(APP));
+    let exposed = header(&res, &ACCESS_CONTROL_EXPOSE_HEADERS).unwrap();
+    assert!(exposed.contains("session") && exposed.contains("token"), "{exposed}");
+    assert_eq!(header(&get("/f/index", OTHER).await, &ACCESS_CONTROL_ALLOW_ORIGIN), None);
+}
+
+#[actix_web::test]
+async fn pages_are_readable_from_anywhere() {
+    let res = get("/", OTHER).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(header(&res, &ACCESS_CONTROL_ALLOW_ORIGIN), Some(OTHER));
+
+    assert!(preflight("/", OTHER, "GET").await.status().is_success());
+    assert!(!preflight("/", OTHER, "DELETE").await.status().is_success());
+}
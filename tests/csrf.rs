@@ -0,0 +1,66 @@
+mod common;
+
+use fup_client::{Client, CreateOptions, SessionCreated, Upload};
+use reqwest::header::COOKIE;
+use common::server;
+
+async fn upload() -> SessionCreated {
+    Client::new(server()).unwrap()
+        .create_session(vec![Upload::bytes("owned.txt", "content")], &CreateOptions::default()).await.unwrap()
+}
+
+/// The CSRF token of the session page the owner's browser gets
+async fn page_csrf(created: &SessionCreated) -> String {
+    let page = reqwest::Client::new().get(format!("{}/f/session/{}", server(), created.session))
+        .header(COOKIE, format!("{}={}", created.session, created.token))
+        .send().await.unwrap().text().await.unwrap();
+    let start = page.find("name=\"csrf-token\" content=\"").unwrap() + "name=\"csrf-token\" content=\"".len();
+    page[start..start + page[start..].find('"').unwrap()].to_string()
+}
+
+/// An owner action as the browser sends it, with the session cookie and maybe a CSRF token
+async fn with_cookie(action: &str, created: &SessionCreated, csrf: Option<&str>) -> u16 {
+    let mut builder = reqwest::Client::new().post(format!("{}/f/{action}/{}", server(), created.session))
+        .header(COOKIE, format!("{}={}", created.session, created.token));
+    if let Some(csrf) = csrf {
+        builder = builder.header("csrf-token", csrf);
+    }
+    builder.send().await.unwrap().status().as_u16()
+}
+
+#[actix_web::test]
+async fn cookies_alone_do_not_act() {
+    let created = upload().await;
+    assert_eq!(with_cookie("sign", &created, None).await, 403);
+    assert_eq!(with_cookie("delete", &created, None).await, 403);
+    assert_eq!(with_cookie("delete", &created, Some("00")).await, 403);
+    assert_eq!(with_cookie("delete", &created, Some("not hex")).await, 403);
+
+    // The token of one session does not carry over to another
+    let other = upload().await;
+    let foreign = page_csrf(&other).await;
+    assert_eq!(with_cookie("delete", &created, Some(&foreign)).await, 403);
+
+    let res = reqwest::get(format!("{}/f/download/{}/owned.txt", server(), created.session)).await.unwrap();
+    assert_eq!(res.status(), 200);
+}
+
+#[actix_web::test]
+async fn the_page_token_acts_with_the_cookie() {
+    let created = upload().await;
+    let csrf = page_csrf(&created).await;
+    assert_eq!(with_cookie("sign", &created, Some(&csrf)).await, 200);
+    assert_eq!(with_cookie("delete", &created, Some(&csrf)).await, 200);
+
+    let res = reqwest::get(format!("{}/f/download/{}/owned.txt", server(), created.session)).await.unwrap();
+    assert_eq!(res.status(), 404);
+}
+
+#[actix_web::test]
+async fn the_token_header_needs_no_csrf() {
+    let created = upload().await;
+    let res = reqwest::Client::new().post(format!("{}/f/delete/{}", server(), created.session))
+        .header("token", &created.token)
+        .send().await.unwrap();
+    assert_eq!(res.status(), 200);
+}
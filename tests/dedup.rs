@@ -0,0 +1,58 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::PathBuf;
+use fup_client::{Client, CreateOptions, SessionCreated, Upload};
+use common::server;
+
+async fn upload(client: &Client, name: &str, content: &str) -> SessionCreated {
+    client.create_session(vec![Upload::bytes(name.to_string(), content.to_string())], &CreateOptions::default()).await.unwrap()
+}
+
+fn blob(created: &SessionCreated) -> PathBuf {
+    let sha256 = created.files[0].sha256.clone().unwrap();
+    PathBuf::from("blobs").join(&sha256[..2]).join(sha256)
+}
+
+#[actix_web::test]
+async fn duplicates_share_one_blob() {
+    let client = Client::new(server()).unwrap();
+    let content = "installer bytes that show up in many sessions";
+    let first = upload(&client, "setup.bin", content).await;
+    let second = upload(&client, "copy.bin", content).await;
+
+    let blob = blob(&first);
+    assert_eq!(blob, self::blob(&second));
+    assert_eq!(fs::metadata(&blob).unwrap().nlink(), 3);
+    let file = fs::metadata(format!("sessions/{}/setup.bin", first.session)).unwrap();
+    assert_eq!(file.ino(), fs::metadata(&blob).unwrap().ino());
+
+    client.delete(&first.session, Some(&first.token)).await.unwrap();
+    assert_eq!(fs::metadata(&blob).unwrap().nlink(), 2);
+    let body = client.download(&second.session, "copy.bin", None).await.unwrap().bytes().await.unwrap();
+    assert_eq!(&body[..], content.as_bytes());
+
+    client.delete(&second.session, Some(&second.token)).await.unwrap();
+    assert!(!blob.exists());
+}
+
+#[actix_web::test]
+async fn repeated_names_cannot_rewrite_shared_blobs() {
+    let client = Client::new(server()).unwrap();
+    let content = "original bytes owned by the first session";
+    let first = upload(&client, "shared.txt", content).await;
+
+    let err = client.create_session(vec![
+        Upload::bytes("shared.txt", content),
+        Upload::bytes("shared.txt", "overwritten through the hard link"),
+    ], &CreateOptions::default()).await.unwrap_err();
+    assert_eq!(err.status().map(|s| s.as_u16()), Some(400));
+    assert!(err.to_string().contains("Duplicate filename"), "{err}");
+
+    assert_eq!(fs::read_to_string(blob(&first)).unwrap(), content);
+    let body = client.download(&first.session, "shared.txt", None).await.unwrap().bytes().await.unwrap();
+    assert_eq!(&body[..], content.as_bytes());
+}
@@ -0,0 +1,42 @@
+mod common;
+
+use fup_client::{Client, CreateOptions, Upload};
+use reqwest::header::{CONTENT_SECURITY_POLICY, REFERRER_POLICY, STRICT_TRANSPORT_SECURITY, X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS};
+use common::server;
+
+fn header<'a>(res: &'a reqwest::Response, name: &reqwest::header::HeaderName) -> &'a str {
+    res.headers().get(name).map(|v| v.to_str().unwrap()).unwrap_or_else(|| panic!("missing {name}"))
+}
+
+#[actix_web::test]
+async fn pages_get_the_page_policy() {
+    for path in ["/", "/f/index", "/admin/reports", "/no/such/page"] {
+        let res = reqwest::get(format!("{}{path}", server())).await.unwrap();
+        assert_eq!(header(&res, &X_CONTENT_TYPE_OPTIONS), "nosniff", "{path}");
+        let csp = header(&res, &CONTENT_SECURITY_POLICY);
+        assert!(csp.contains("script-src 'self'") && csp.contains("frame-ancestors 'none'"), "{path}: {csp}");
+        assert!(!csp.contains("sandbox"), "{path}: {csp}");
+        assert_eq!(header(&res, &X_FRAME_OPTIONS), "DENY", "{path}");
+        assert_eq!(header(&res, &REFERRER_POLICY), "same-origin", "{path}");
+        assert!(header(&res, &STRICT_TRANSPORT_SECURITY).starts_with("max-age="), "{path}");
+    }
+}
+
+#[actix_web::test]
+async fn user_content_is_sandboxed() {
+    let created = Client::new(server()).unwrap()
+        .create_session(vec![Upload::bytes("page.html", "<script>alert(1)</script>")], &CreateOptions::default()).await.unwrap();
+
+    let res = reqwest::get(format!("{}/f/download/{}/page.html", server(), created.session)).await.unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(header(&res, &X_CONTENT_TYPE_OPTIONS), "nosniff");
+    let csp = header(&res, &CONTENT_SECURITY_POLICY);
+    assert!(csp.contains("sandbox") && csp.contains("default-src 'none'"), "{csp}");
+    assert!(!csp.contains("script-src"), "{csp}");
+    assert_eq!(header(&res, &X_FRAME_OPTIONS), "DENY");
+
+    // Error pages on the same routes are rendered by the app
+    let res = reqwest::get(format!("{}/f/download/{}/missing.html", server(), created.session)).await.unwrap();
+    assert_eq!(res.status(), 404);
+    assert!(!header(&res, &CONTENT_SECURITY_POLICY).contains("sandbox"));
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use fup_client::{Client, CreateOptions, SessionCreated, Upload};
+use reqwest::header::{CONTENT_DISPOSITION, CONTENT_SECURITY_POLICY, CONTENT_TYPE, X_FRAME_OPTIONS};
+use common::server;
+
+async fn upload(files: Vec<Upload>, signed_only: bool) -> SessionCreated {
+    let options = CreateOptions { signed_only, ..CreateOptions::default() };
+    Client::new(server()).unwrap().create_session(files, &options).await.unwrap()
+}
+
+async fn preview(session: &str, file: &str) -> reqwest::Response {
+    reqwest::get(format!("{}/f/preview/{session}/{file}", server())).await.unwrap()
+}
+
+#[actix_web::test]
+async fn serves_allowlisted_types_inline() {
+    let created = upload(vec![
+        Upload::bytes("photo.png", &b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR"[..]),
+        Upload::bytes("fake.png", "not really a png"),
+        Upload::bytes("page.html", "<script>alert(1)</script>"),
+        Upload::bytes("src/main.rs", "fn main() {}"),
+        Upload::bytes("setup.exe", "MZ"),
+    ], false).await;
+
+    let res = preview(&created.session, "photo.png").await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()[CONTENT_TYPE], "image/png");
+    assert!(res.headers()[CONTENT_DISPOSITION].to_str().unwrap().starts_with("inline"));
+    let csp = res.headers()[CONTENT_SECURITY_POLICY].to_str().unwrap();
+    assert!(csp.contains("sandbox") && csp.contains("frame-ancestors"), "{csp}");
+    assert!(!res.headers().contains_key(X_FRAME_OPTIONS));
+
+    for file in ["page.html", "src/main.rs", "fake.png"] {
+        let res = preview(&created.session, file).await;
+        assert_eq!(res.headers()[CONTENT_TYPE], "text/plain; charset=utf-8", "{file}");
+    }
+    assert_eq!(preview(&created.session, "setup.exe").await.status(), 415);
+    assert_eq!(preview(&created.session, "missing.png").await.status(), 404);
+}
+
+#[actix_web::test]
+async fn signed_only_previews_need_a_signature() {
+    let created = upload(vec![Upload::bytes("photo.png", "png")], true).await;
+    assert_eq!(preview(&created.session, "photo.png").await.status(), 403);
+
+    let client = Client::new(server()).unwrap();
+    let link = client.sign(&created.session, &created.token, Some("photo.png"), None).await.unwrap();
+    let res = preview(&created.session, &format!("photo.png?{}", link.query().unwrap())).await;
+    assert_eq!(res.status(), 200);
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use std::sync::OnceLock;
+use fup_client::{Client, CreateOptions, SessionCreated, Upload};
+use reqwest::header::RETRY_AFTER;
+use reqwest::Method;
+
+/// Three metadata requests per client key, refilled once a minute
+fn server() -> &'static str {
+    static SERVER: OnceLock<String> = OnceLock::new();
+    SERVER.get_or_init(|| common::start("trusted_proxies = [\"127.0.0.1\"]\n\n\
+        [rate_limit]\nenabled = true\n\
+        metadata = { capacity = 3.0, refill_per_sec = 0.016 }\n\
+        auth_failure = { capacity = 100.0, refill_per_sec = 1.0 }\n\n\
+        [cors.api]\nallowed_origins = [\"https://app.example.org\"]\n"))
+}
+
+async fn session() -> SessionCreated {
+    Client::new(server()).unwrap()
+        .create_session(vec![Upload::bytes("a.txt", "a")], &CreateOptions::default()).await.unwrap()
+}
+
+/// Asks for the session's info as client `ip`
+async fn info(session: &str, ip: &str, token: &str) -> reqwest::Response {
+    reqwest::Client::new().get(format!("{}/f/get-info", server()))
+        .header("x-forwarded-for", ip)
+        .header("session", session)
+        .header("token", token)
+        .send().await.unwrap()
+}
+
+#[actix_web::test]
+async fn exhausted_buckets_answer_429() {
+    let created = session().await;
+    for _ in 0..3 {
+        assert_eq!(info(&created.session, "198.51.100.1", "").await.status(), 200);
+    }
+    let res = info(&created.session, "198.51.100.1", "").await;
+    assert_eq!(res.status(), 429);
+    let retry_after: u64 = res.headers()[RETRY_AFTER].to_str().unwrap().parse().unwrap();
+    assert!((1..=63).contains(&retry_after), "{retry_after}");
+
+    // Other clients have their own buckets
+    assert_eq!(info(&created.session, "198.51.100.2", "").await.status(), 200);
+
+    let res = reqwest::Client::new().get(format!("{}/api/v1/sessions/{}", server(), created.session))
+        .header("x-forwarded-for", "198.51.100.1")
+        .send().await.unwrap();
+    assert_eq!(res.status(), 429);
+    assert_eq!(res.json::<serde_json::Value>().await.unwrap()["error"]["code"], "rate_limited");
+}
+
+#[actix_web::test]
+async fn preflights_are_not_limited() {
+    let created = session().await;
+    let url = format!("{}/api/v1/sessions/{}", server(), created.session);
+    for _ in 0..10 {
+        let res = reqwest::Client::new().request(Method::OPTIONS, &url)
+            .header("x-forwarded-for", "198.51.100.3")
+            .header("origin", "https://app.example.org")
+            .header("access-control-request-method", "GET")
+            .send().await.unwrap();
+        assert_ne!(res.status(), 429);
+    }
+    assert_eq!(info(&created.session, "198.51.100.3", "").await.status(), 200);
+}
+
+#[actix_web::test]
+async fn only_valid_tokens_share_a_bucket() {
+    let created = session().await;
+    // Made up tokens are ignored, every address keeps its own budget
+    for i in 0..5 {
+        assert_eq!(info(&created.session, &format!("203.0.113.{i}"), "madeup").await.status(), 200);
+    }
+    // The owner token is limited across addresses
+    for i in 10..13 {
+        assert_eq!(info(&created.session, &format!("203.0.113.{i}"), &created.token).await.status(), 200);
+    }
+    assert_eq!(info(&created.session, "203.0.113.20", &created.token).await.status(), 429);
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+use reqwest::Url;
+use common::server;
+
+/// `curl -T` style upload to `path` below `/f/`
+async fn put(path: &str, body: &str, headers: &[(&str, &str)]) -> reqwest::Response {
+    let mut builder = reqwest::Client::new().put(format!("{}/f/{path}", server())).body(body.to_string());
+    for (name, value) in headers {
+        builder = builder.header(*name, *value);
+    }
+    builder.send().await.unwrap()
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+/// The link of a plain text answer, sent to the test server instead of the public hostname
+fn link(answer: &str) -> String {
+    let url = Url::parse(answer.lines().next().unwrap()).unwrap();
+    match url.query() {
+        Some(query) => format!("{}{}?{query}", server(), url.path()),
+        None => format!("{}{}", server(), url.path()),
+    }
+}
+
+#[actix_web::test]
+async fn answers_with_a_link_and_the_owner_token() {
+    let res = put("notes.txt", "put me", &[]).await;
+    assert_eq!(res.status(), 200);
+    let session = res.headers()["session"].to_str().unwrap().to_string();
+    let token = res.headers()["token"].to_str().unwrap().to_string();
+    let answer = res.text().await.unwrap();
+    assert!(answer.ends_with(&format!("Owner-Token: {token}\n")), "{answer}");
+
+    let url = link(&answer);
+    assert!(url.ends_with(&format!("/f/download/{session}/notes.txt")), "{url}");
+    assert_eq!(reqwest::get(url).await.unwrap().text().await.unwrap(), "put me");
+}
+
+#[actix_web::test]
+async fn names_come_from_the_path_or_query() {
+    let answer = put("?name=query.txt", "by query", &[]).await.text().await.unwrap();
+    assert!(link(&answer).ends_with("/query.txt"), "{answer}");
+    let answer = put("", "unnamed", &[]).await.text().await.unwrap();
+    assert!(link(&answer).ends_with("/stdin"), "{answer}");
+}
+
+#[actix_web::test]
+async fn lifetimes_take_units() {
+    for (value, seconds) in [("90", 90), ("30m", 30 * 60), ("2h", 2 * 60 * 60), ("7d", 7 * 24 * 60 * 60)] {
+        let res = put("short.txt", "content", &[("expiration", value)]).await;
+        assert_eq!(res.status(), 200, "{value}");
+        let expires: u64 = res.headers()["expiration"].to_str().unwrap().parse().unwrap();
+        assert!(expires.abs_diff(now() + seconds) <= 5, "{value}");
+    }
+    let res = put("short.txt?expires=1h", "content", &[]).await;
+    let expires: u64 = res.headers()["expiration"].to_str().unwrap().parse().unwrap();
+    assert!(expires.abs_diff(now() + 60 * 60) <= 5);
+}
+
+#[actix_web::test]
+async fn refuses_invalid_or_overlong_lifetimes() {
+    // The server allows 90 days
+    for value in ["soon", "-1", "1w", "91d", "7777777", "18446744073709551615", "18446744073709551615d", "213503982334602d"] {
+        let res = put("long.txt", "content", &[("expiration", value)]).await;
+        assert_eq!(res.status(), 400, "{value}");
+    }
+    assert_eq!(put("long.txt?expires=100d", "content", &[]).await.status(), 400);
+}
+
+#[actix_web::test]
+async fn signed_only_uploads_answer_a_signed_link() {
+    let res = put("secret.txt", "signed", &[("signed-only", "true")]).await;
+    let session = res.headers()["session"].to_str().unwrap().to_string();
+    let url = link(&res.text().await.unwrap());
+    assert!(url.contains("sig="), "{url}");
+    assert_eq!(reqwest::get(&url).await.unwrap().text().await.unwrap(), "signed");
+    let unsigned = reqwest::get(format!("{}/f/download/{session}/secret.txt", server())).await.unwrap();
+    assert_eq!(unsigned.status(), 403);
+}
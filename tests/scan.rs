@@ -0,0 +1,85 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::io::{Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::OnceLock;
+use std::thread;
+use fup_client::{Client, CreateOptions, SessionCreated, Upload};
+use common::server;
+
+const EICAR: &str = "X5O!P%@AP[4\\PZX54(P^)7CC)7}$EICAR-STANDARD-ANTIVIRUS-TEST-FILE!$H+H*";
+
+/// Speaks just enough of the clamd protocol, reporting the EICAR test string
+fn clamd() {
+    static STUB: OnceLock<()> = OnceLock::new();
+    STUB.get_or_init(|| {
+        server();
+        let listener = UnixListener::bind("clamd.sock").unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                thread::spawn(move || answer(stream.unwrap()));
+            }
+        });
+    });
+}
+
+fn answer(mut stream: UnixStream) {
+    let mut command = [0; 10];
+    stream.read_exact(&mut command).unwrap();
+    assert_eq!(&command, b"zINSTREAM\0");
+    let mut content = vec![];
+    loop {
+        let mut length = [0; 4];
+        stream.read_exact(&mut length).unwrap();
+        let length = u32::from_be_bytes(length) as usize;
+        if length == 0 {
+            break
+        }
+        let start = content.len();
+        content.resize(start + length, 0);
+        stream.read_exact(&mut content[start..]).unwrap();
+    }
+    let reply = match String::from_utf8_lossy(&content).contains("EICAR-STANDARD-ANTIVIRUS-TEST-FILE") {
+        true => "stream: Eicar-Test-Signature FOUND\0",
+        false => "stream: OK\0",
+    };
+    stream.write_all(reply.as_bytes()).unwrap();
+}
+
+async fn upload(files: Vec<Upload>) -> SessionCreated {
+    clamd();
+    Client::new(server()).unwrap().create_session(files, &CreateOptions::default()).await.unwrap()
+}
+
+async fn session_page(session: &str) -> String {
+    reqwest::get(format!("{}/f/session/{session}", server())).await.unwrap().text().await.unwrap()
+}
+
+#[actix_web::test]
+async fn clean_files_are_marked_scanned() {
+    let created = upload(vec![Upload::bytes("readme.txt", "nothing to see")]).await;
+    let meta = std::fs::read_to_string(format!("sessions/{}/.meta", created.session)).unwrap();
+    assert!(meta.contains("\"scan\":{\"status\":\"clean\"}"), "{meta}");
+    assert!(session_page(&created.session).await.contains("class=\"scan-clean\""));
+
+    let res = reqwest::get(format!("{}/f/download/{}/readme.txt", server(), created.session)).await.unwrap();
+    assert_eq!(res.status(), 200);
+}
+
+#[actix_web::test]
+async fn detected_files_are_quarantined() {
+    let created = upload(vec![
+        Upload::bytes("readme.txt", "fine"),
+        Upload::bytes("eicar.com", EICAR),
+    ]).await;
+    let eicar = created.files.iter().find(|f| f.name == "eicar.com").unwrap();
+    assert!(eicar.quarantined);
+    assert!(!created.files.iter().find(|f| f.name == "readme.txt").unwrap().quarantined);
+
+    let res = reqwest::get(format!("{}/f/download/{}/eicar.com", server(), created.session)).await.unwrap();
+    assert_eq!(res.status(), 403);
+    let page = session_page(&created.session).await;
+    assert!(page.contains("title=\"Malware scanner: Eicar-Test-Signature\">Quarantined"), "{page}");
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use std::time::Duration;
+use fup_client::{Client, CreateOptions, SessionCreated, Upload};
+use reqwest::Url;
+use common::server;
+
+async fn upload(files: &[(&str, &str)], signed_only: bool) -> SessionCreated {
+    let uploads = files.iter().map(|(name, content)| Upload::bytes(name.to_string(), content.to_string())).collect();
+    let options = CreateOptions { signed_only, ..CreateOptions::default() };
+    Client::new(server()).unwrap().create_session(uploads, &options).await.unwrap()
+}
+
+/// Asks for a signed link as the owner, `headers` are sent along
+async fn sign(created: &SessionCreated, headers: &[(&str, &str)]) -> reqwest::Response {
+    let mut builder = reqwest::Client::new().post(format!("{}/f/sign/{}", server(), created.session))
+        .header("token", &created.token);
+    for (name, value) in headers {
+        builder = builder.header(*name, *value);
+    }
+    builder.send().await.unwrap()
+}
+
+/// The signed links point at the public hostname, the tests send them to the test server
+async fn link(created: &SessionCreated, headers: &[(&str, &str)]) -> String {
+    let res = sign(created, headers).await;
+    assert_eq!(res.status(), 200);
+    let url = Url::parse(&res.text().await.unwrap()).unwrap();
+    format!("{}{}?{}", server(), url.path(), url.query().unwrap())
+}
+
+async fn get(url: &str, forwarded_for: Option<&str>) -> reqwest::Response {
+    let mut builder = reqwest::Client::new().get(url);
+    if let Some(ip) = forwarded_for {
+        builder = builder.header("x-forwarded-for", ip);
+    }
+    builder.send().await.unwrap()
+}
+
+#[actix_web::test]
+async fn signed_only_sessions_need_a_link() {
+    let created = upload(&[("a.txt", "first"), ("b.txt", "second")], true).await;
+    let session = &created.session;
+
+    assert_eq!(get(&format!("{}/f/download/{session}/a.txt", server()), None).await.status(), 403);
+    assert_eq!(get(&format!("{}/f/download/{session}", server()), None).await.status(), 403);
+
+    let file_link = link(&created, &[("file", "a.txt")]).await;
+    let res = get(&file_link, None).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.text().await.unwrap(), "first");
+    // The signature covers the file it was made for
+    assert_eq!(get(&file_link.replace("/a.txt?", "/b.txt?"), None).await.status(), 403);
+    assert_eq!(get(&file_link.replace("sig=", "sig=00"), None).await.status(), 403);
+    assert_eq!(get(&format!("{file_link}&all=1"), None).await.status(), 403);
+
+    let session_link = link(&created, &[]).await;
+    assert!(session_link.contains("all=1"));
+    assert_eq!(get(&session_link, None).await.status(), 200);
+    let file_of_session = session_link.replace(&format!("/{session}?"), &format!("/{session}/b.txt?"));
+    assert_eq!(get(&file_of_session, None).await.text().await.unwrap(), "second");
+}
+
+#[actix_web::test]
+async fn only_owners_sign_links() {
+    let created = upload(&[("owned.txt", "content")], true).await;
+    let res = reqwest::Client::new().post(format!("{}/f/sign/{}", server(), created.session))
+        .header("token", "not-the-token")
+        .send().await.unwrap();
+    assert_eq!(res.status(), 403);
+    assert_eq!(sign(&created, &[("file", "missing.txt")]).await.status(), 404);
+    assert_eq!(sign(&created, &[("expires-in", "soon")]).await.status(), 400);
+}
+
+#[actix_web::test]
+async fn links_expire() {
+    let created = upload(&[("brief.txt", "content")], true).await;
+    let res = sign(&created, &[("file", "brief.txt"), ("expires-in", "0")]).await;
+    let expires: u64 = res.headers()["expiration"].to_str().unwrap().parse().unwrap();
+    let url = Url::parse(&res.text().await.unwrap()).unwrap();
+    assert!(url.query().unwrap().contains(&format!("expires={expires}")));
+
+    actix_web::rt::time::sleep(Duration::from_millis(2100)).await;
+    let expired = format!("{}{}?{}", server(), url.path(), url.query().unwrap());
+    assert_eq!(get(&expired, None).await.status(), 403);
+    // Pushing the expiration back breaks the signature
+    let extended = expired.replace(&format!("expires={expires}"), &format!("expires={}", expires + 3600));
+    assert_eq!(get(&extended, None).await.status(), 403);
+}
+
+#[actix_web::test]
+async fn bound_links_follow_the_client() {
+    let created = upload(&[("bound.txt", "content")], true).await;
+    let res = reqwest::Client::new().post(format!("{}/f/sign/{}", server(), created.session))
+        .header("token", &created.token)
+        .header("file", "bound.txt")
+        .header("bind-ip", "true")
+        .header("x-forwarded-for", "203.0.113.5")
+        .send().await.unwrap();
+    assert_eq!(res.status(), 200);
+    let url = Url::parse(&res.text().await.unwrap()).unwrap();
+    assert!(url.query().unwrap().contains("ip=1"));
+    let bound = format!("{}{}?{}", server(), url.path(), url.query().unwrap());
+
+    assert_eq!(get(&bound, Some("203.0.113.5")).await.status(), 200);
+    assert_eq!(get(&bound, Some("203.0.113.6")).await.status(), 403);
+    assert_eq!(get(&bound.replace("&ip=1", ""), Some("203.0.113.5")).await.status(), 403);
+}
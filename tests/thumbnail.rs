@@ -0,0 +1,43 @@
+mod common;
+
+use std::io::Cursor;
+use fup_client::{Client, CreateOptions, Upload};
+use image::{ImageFormat, RgbImage};
+use reqwest::header::CONTENT_TYPE;
+use common::server;
+
+fn png(width: u32, height: u32) -> Vec<u8> {
+    let mut bytes = Cursor::new(vec![]);
+    RgbImage::new(width, height).write_to(&mut bytes, ImageFormat::Png).unwrap();
+    bytes.into_inner()
+}
+
+async fn thumb(session: &str, file: &str) -> reqwest::Response {
+    reqwest::get(format!("{}/f/thumb/{session}/{file}", server())).await.unwrap()
+}
+
+#[actix_web::test]
+async fn thumbnails_are_generated_and_cached() {
+    let created = Client::new(server()).unwrap().create_session(vec![
+        Upload::bytes("photos/wide.png", png(1000, 500)),
+        Upload::bytes("fake.png", "not an image"),
+        Upload::bytes("huge.png", png(20_000, 1)),
+        Upload::bytes("notes.txt", "text"),
+    ], &CreateOptions::default()).await.unwrap();
+
+    for _ in 0..2 {
+        let res = thumb(&created.session, "photos/wide.png").await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.headers()[CONTENT_TYPE], "image/jpeg");
+        let thumbnail = image::load_from_memory(&res.bytes().await.unwrap()).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (256, 128));
+    }
+    assert_eq!(thumb(&created.session, "fake.png").await.status(), 415);
+    assert_eq!(thumb(&created.session, "huge.png").await.status(), 415);
+    assert_eq!(thumb(&created.session, "notes.txt").await.status(), 404);
+
+    // The cache stays out of listings and archives
+    let files = Client::new(server()).unwrap().files(&created.session, None).await.unwrap();
+    assert_eq!(files.len(), 4);
+    assert!(files.iter().all(|f| !f.name.contains(".thumbs")));
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use fup_client::{Client, CreateOptions, SessionCreated, Upload};
+use reqwest::header::CONTENT_TYPE;
+use common::server;
+
+async fn upload(files: Vec<Upload>) -> SessionCreated {
+    Client::new(server()).unwrap().create_session(files, &CreateOptions::default()).await.unwrap()
+}
+
+async fn view(session: &str, file: &str) -> (u16, String) {
+    let res = reqwest::get(format!("{}/f/view/{session}/{file}", server())).await.unwrap();
+    (res.status().as_u16(), res.text().await.unwrap())
+}
+
+#[actix_web::test]
+async fn source_is_highlighted_with_line_anchors() {
+    let long: String = (0..60_000).map(|i| format!("line {i}\n")).collect();
+    let created = upload(vec![
+        Upload::bytes("src/main.rs", "fn main() {\n    println!(\"{{content}} <b>\");\n}\n"),
+        Upload::bytes("huge.log", long),
+        Upload::bytes("setup.exe", "MZ"),
+        Upload::bytes("template.txt", "{{summary}}"),
+    ]).await;
+
+    let (status, page) = view(&created.session, "src/main.rs").await;
+    assert_eq!(status, 200);
+    assert!(page.contains("Rust, 3 lines"), "{page}");
+    assert!(page.contains("id=\"L2\"") && page.contains("href=\"#L3\""));
+    assert!(page.contains("class=\"hl-"));
+    assert!(page.contains("content") && page.contains("&lt;b&gt;") && !page.contains("<b>"));
+
+    let (status, page) = view(&created.session, "huge.log").await;
+    assert_eq!(status, 200);
+    assert!(page.contains("too large to show completely"));
+    assert!(page.contains("id=\"L10000\"") && !page.contains("id=\"L10001\""));
+
+    // Placeholders within the file are shown as they are
+    let (_, page) = view(&created.session, "template.txt").await;
+    assert!(page.contains("{{summary}}"), "{page}");
+    assert_eq!(view(&created.session, "setup.exe").await.0, 404);
+
+    let res = reqwest::get(format!("{}/r/highlight.css", server())).await.unwrap();
+    assert!(res.headers()[CONTENT_TYPE].to_str().unwrap().starts_with("text/css"));
+    assert!(res.text().await.unwrap().contains(".hl-"));
+}
+
+#[actix_web::test]
+async fn markdown_is_rendered_safely() {
+    let created = upload(vec![Upload::bytes("README.md", concat!(
+        "# Title\n\n",
+        "<script>alert(1)</script>\n\n",
+        "[bad](javascript:alert(1)) [spaced]( JaVaScRiPt:alert(1)) [good](https://example.com) [local](docs/intro.md)\n",
+    ))]).await;
+
+    let (status, page) = view(&created.session, "README.md").await;
+    assert_eq!(status, 200);
+    assert!(page.contains("<h1>Title</h1>"));
+    assert!(page.contains("&lt;script&gt;") && !page.contains("<script>alert"));
+    assert!(!page.to_lowercase().contains("javascript:"), "{page}");
+    assert!(page.contains("href=\"https://example.com\"") && page.contains("href=\"docs/intro.md\""));
+}